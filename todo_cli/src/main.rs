@@ -0,0 +1,283 @@
+//! `todo`: edit the JSON task list from the shell.
+//!
+//! Tasks are referred to by their number in `todo list`, which counts every task in the file
+//! whatever the filter, or by a prefix of their id.
+//!
+//! The file is read and written whole on every command. The druid app does the same but keeps
+//! its list in memory, so its next save overwrites whatever the CLI changed while it was
+//! running. Close the app before editing its list from the shell.
+
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+
+use chrono::Local;
+use todo_core::json::{self, StoredTask};
+use todo_core::{Completable, Filter};
+
+const USAGE: &str = "\
+Usage: todo [--file PATH] <command> [args]
+
+Commands:
+  add <text>...              Add a task
+  list [--filter FILTER]     List tasks; FILTER is all, active or completed
+  done <task>...             Mark tasks as completed
+  undone <task>...           Mark tasks as not completed
+  edit <task> <text>...      Change a task's description
+  rm <task>...               Delete tasks
+  clear-completed            Delete all completed tasks
+
+<task> is a number from `todo list` or a prefix of the task's id.
+The file defaults to $TODO_FILE, or else the druid app's list.
+Close the app first: it doesn't pick up changes and saves over them.";
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+struct Args {
+    file: Option<PathBuf>,
+    filter: Filter,
+    command: String,
+    rest: Vec<String>,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("todo: {e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("todo: {e}");
+        process::exit(1);
+    }
+}
+
+/// `Ok(None)` means help was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>> {
+    let mut file = None;
+    let mut filter = Filter::All;
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--file" => file = Some(PathBuf::from(value("--file")?)),
+            "--filter" => filter = value("--filter")?.parse()?,
+            // Everything after `--` is text, even if it looks like a flag
+            "--" => positional.extend(args.by_ref()),
+            _ if flag.starts_with('-') && flag.len() > 1 => return Err(format!("unknown option {flag}").into()),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let Some(command) = positional.next() else {
+        return Ok(None);
+    };
+    Ok(Some(Args {
+        file,
+        filter,
+        command,
+        rest: positional.collect(),
+    }))
+}
+
+fn run(args: Args) -> Result<()> {
+    let path = args.file
+        .or_else(json::default_path)
+        .ok_or("no task file; pass --file or set $TODO_FILE")?;
+    let mut tasks = json::load(&path)?;
+    let today = Local::now().date_naive();
+
+    match args.command.as_str() {
+        "add" => {
+            let description = join_text(&args.rest)?;
+            let mut task = StoredTask::new(description);
+            task.created = Some(today);
+            tasks.push(task);
+            print_task(tasks.len() - 1, &tasks[tasks.len() - 1]);
+        }
+        "list" | "ls" => {
+            for (i, task) in tasks.iter().enumerate().filter(|(_, task)| args.filter.matches(*task)) {
+                print_task(i, task);
+            }
+            return Ok(());
+        }
+        "done" | "undone" => {
+            let completed = args.command == "done";
+            for i in resolve_all(&tasks, &args.rest)? {
+                let task = &mut tasks[i];
+                if task.is_completed() != completed {
+                    task.set_completed(completed);
+                    task.completed_on = completed.then_some(today);
+                }
+                print_task(i, task);
+            }
+        }
+        "edit" => {
+            let (reference, text) = args.rest.split_first().ok_or("edit needs a task and the new text")?;
+            let i = resolve(&tasks, reference)?;
+            tasks[i].description = join_text(text)?;
+            print_task(i, &tasks[i]);
+        }
+        "rm" => {
+            let indices = resolve_all(&tasks, &args.rest)?;
+            remove_all(&mut tasks, indices);
+        }
+        "clear-completed" => {
+            let before = tasks.len();
+            tasks.retain(|task| Filter::Active.matches(task));
+            let removed = before - tasks.len();
+            println!("removed {} {}", removed, todo_core::task_noun(removed));
+        }
+        other => return Err(format!("unknown command {other}").into()),
+    }
+
+    json::save(&path, &tasks)?;
+    Ok(())
+}
+
+fn print_task(i: usize, task: &StoredTask) {
+    let mark = if task.is_completed() { 'x' } else { ' ' };
+    println!("{:>3} [{}] {}", i + 1, mark, task.description);
+}
+
+fn join_text(words: &[String]) -> Result<String> {
+    let text = words.join(" ");
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("the task text is empty".into());
+    }
+    Ok(text.to_string())
+}
+
+/// Removes the tasks at `indices`, each once however often it's given.
+fn remove_all(tasks: &mut Vec<StoredTask>, mut indices: Vec<usize>) {
+    // Back to front, so the remaining indices stay valid
+    indices.sort_unstable_by(|a, b| b.cmp(a));
+    indices.dedup();
+    for i in indices {
+        tasks.remove(i);
+    }
+}
+
+fn resolve_all(tasks: &[StoredTask], references: &[String]) -> Result<Vec<usize>> {
+    if references.is_empty() {
+        return Err("no task given".into());
+    }
+    references.iter().map(|reference| resolve(tasks, reference)).collect()
+}
+
+/// Finds a task by its 1-based number or by an id prefix.
+fn resolve(tasks: &[StoredTask], reference: &str) -> Result<usize> {
+    if let Ok(number) = reference.parse::<usize>() {
+        return number.checked_sub(1)
+            .filter(|i| *i < tasks.len())
+            .ok_or_else(|| format!("no task {number}").into());
+    }
+
+    let prefix = reference.to_ascii_lowercase();
+    let mut found = tasks.iter()
+        .enumerate()
+        .filter(|(_, task)| task.id.to_string().starts_with(&prefix))
+        .map(|(i, _)| i);
+    match (found.next(), found.next()) {
+        (Some(i), None) => Ok(i),
+        (Some(_), Some(_)) => Err(format!("{reference} matches more than one task").into()),
+        (None, _) => Err(format!("no task with id {reference}").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn tasks_with_ids(ids: &[&str]) -> Vec<StoredTask> {
+        ids.iter()
+            .map(|id| {
+                let mut task = StoredTask::new(format!("task {id}"));
+                task.id = id.parse().unwrap();
+                task
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_flags_anywhere() {
+        let args = parse(&["add", "--file=todo.json", "buy", "--filter", "active", "milk"]).unwrap().unwrap();
+        assert_eq!(args.file, Some(PathBuf::from("todo.json")));
+        assert_eq!(args.filter, Filter::Active);
+        assert_eq!(args.command, "add");
+        assert_eq!(args.rest, ["buy", "milk"]);
+
+        let args = parse(&["-f", "todo.json", "list"]).unwrap().unwrap();
+        assert_eq!(args.file, Some(PathBuf::from("todo.json")));
+    }
+
+    #[test]
+    fn text_after_double_dash_is_not_parsed() {
+        let args = parse(&["add", "--", "--help", "-f"]).unwrap().unwrap();
+        assert_eq!(args.rest, ["--help", "-f"]);
+        assert_eq!(args.file, None);
+    }
+
+    #[test]
+    fn help_and_bad_flags() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&["list", "--help"]).unwrap().is_none());
+        assert!(parse(&["list", "--file"]).is_err());
+        assert!(parse(&["list", "--filter", "someday"]).is_err());
+        assert!(parse(&["list", "--verbose"]).is_err());
+        // A lone `-` is an argument, not a flag
+        assert_eq!(parse(&["add", "-"]).unwrap().unwrap().rest, ["-"]);
+    }
+
+    #[test]
+    fn resolves_numbers_and_id_prefixes() {
+        let tasks = tasks_with_ids(&[
+            "1a2b3c4d-0000-4000-8000-000000000000",
+            "1a2bffff-0000-4000-8000-000000000000",
+            "9f000000-0000-4000-8000-000000000000",
+        ]);
+        assert_eq!(resolve(&tasks, "2").unwrap(), 1);
+        assert!(resolve(&tasks, "0").is_err());
+        assert!(resolve(&tasks, "4").is_err());
+
+        assert_eq!(resolve(&tasks, "9F").unwrap(), 2);
+        assert_eq!(resolve(&tasks, "1a2b3").unwrap(), 0);
+        let ambiguous = resolve(&tasks, "1a2b").unwrap_err();
+        assert!(ambiguous.to_string().contains("more than one"), "{ambiguous}");
+        assert!(resolve(&tasks, "abc").is_err());
+        assert!(resolve_all(&tasks, &[]).is_err());
+    }
+
+    #[test]
+    fn removes_each_task_once_in_any_order() {
+        let mut tasks = (1..=5).map(|n| StoredTask::new(n.to_string())).collect::<Vec<_>>();
+        remove_all(&mut tasks, vec![1, 4, 1, 3]);
+        let left = tasks.iter().map(|task| task.description.as_str()).collect::<Vec<_>>();
+        assert_eq!(left, ["1", "3"]);
+    }
+}
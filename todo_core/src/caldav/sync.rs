@@ -0,0 +1,306 @@
+//! Two-way sync of a local todo list with a collection.
+//!
+//! The app remembers, for every todo it synced, the resource's href and ETag and the todo as
+//! it was then (the base). A different ETag on the server means someone else changed the
+//! todo. If it also changed locally the two are merged field by field against the base, so
+//! only a field both sides changed is a real conflict, and there the local edit wins.
+
+use super::{by_uid, Client, Resource, Result};
+use crate::ical::VTodo;
+
+/// Where a synced todo is stored on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub href: String,
+    pub etag: Option<String>,
+}
+
+/// A todo of the local list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalTask {
+    pub todo: VTodo,
+    /// `None` if it was never synced.
+    pub remote: Option<Remote>,
+    /// The todo as of the last sync.
+    pub base: Option<VTodo>,
+    /// Changed since the last sync.
+    pub dirty: bool,
+}
+
+/// A synced todo that was deleted locally and still has to be deleted on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tombstone {
+    pub uid: String,
+    pub href: String,
+    pub etag: Option<String>,
+}
+
+/// What happened in a sync, and what's left to do to the local list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Todos to store, adding those that are new, and to use as the base from now on.
+    pub synced: Vec<(VTodo, Remote)>,
+    /// UIDs of the todos to remove because they were deleted on the server.
+    pub removed: Vec<String>,
+    /// Todos sent to the server, merged ones included.
+    pub uploaded: usize,
+    /// New or changed todos taken from the server.
+    pub downloaded: usize,
+    /// Todos deleted on the server or because they were deleted there.
+    pub deleted: usize,
+    /// Todos changed on both sides.
+    pub merged: usize,
+}
+
+impl Report {
+    fn synced(&mut self, todo: VTodo, href: String, etag: Option<String>) {
+        self.synced.push((todo, Remote { href, etag }));
+    }
+
+    fn download(&mut self, resource: Resource) {
+        self.downloaded += 1;
+        self.synced(resource.todo, resource.href, resource.etag);
+    }
+}
+
+/// Brings the server and `local` in line with each other. `tombstones` are the synced todos
+/// deleted locally since the last sync; afterwards they are all dealt with.
+///
+/// Nothing is changed locally, the report says what the caller has to change. If the sync
+/// fails halfway, the next one finds what was already sent as changes on both sides and
+/// merges them.
+pub fn sync(client: &Client, local: &[LocalTask], tombstones: &[Tombstone]) -> Result<Report> {
+    let mut remote = by_uid(client.list()?);
+    let mut report = Report::default();
+
+    for tombstone in tombstones {
+        match remote.remove(&tombstone.uid) {
+            // Gone already
+            None => {}
+            Some(resource) if resource.etag == tombstone.etag => {
+                client.delete(&resource.href, resource.etag.as_deref())?;
+                report.deleted += 1;
+            }
+            // Edited on the server after it was deleted here, so it comes back
+            Some(resource) => report.download(resource),
+        }
+    }
+
+    for task in local {
+        let todo = &task.todo;
+        match (&task.remote, remote.remove(&todo.uid)) {
+            (None, None) => {
+                let href = client.href_for(&todo.uid);
+                let etag = client.put(&href, todo, None)?;
+                report.uploaded += 1;
+                report.synced(todo.clone(), href, etag);
+            }
+            // Deleted on the server
+            (Some(_), None) if !task.dirty => {
+                report.deleted += 1;
+                report.removed.push(todo.uid.clone());
+            }
+            // Deleted on the server but changed here since, which is kept
+            (Some(ours), None) => {
+                let etag = client.put(&ours.href, todo, None)?;
+                report.uploaded += 1;
+                report.synced(todo.clone(), ours.href.clone(), etag);
+            }
+            (Some(ours), Some(theirs)) if ours.etag == theirs.etag => {
+                if task.dirty {
+                    let etag = client.put(&theirs.href, todo, theirs.etag.as_deref())?;
+                    report.uploaded += 1;
+                    report.synced(todo.clone(), theirs.href, etag);
+                }
+            }
+            (Some(_), Some(theirs)) if !task.dirty => report.download(theirs),
+            // Changed on both sides, or created on both with the same UID
+            (_, Some(theirs)) => {
+                let merged = merge(task.base.as_ref(), todo, &theirs.todo);
+                let etag = client.put(&theirs.href, &merged, theirs.etag.as_deref())?;
+                report.uploaded += 1;
+                report.merged += 1;
+                report.synced(merged, theirs.href, etag);
+            }
+        }
+    }
+
+    for resource in remote.into_values() {
+        report.download(resource);
+    }
+    Ok(report)
+}
+
+/// `remote` with the fields `local` changed since `base` taken from `local`. Without a base
+/// every field that differs is taken from `local`.
+pub fn merge(base: Option<&VTodo>, local: &VTodo, remote: &VTodo) -> VTodo {
+    let mut merged = remote.clone();
+    macro_rules! take_local {
+        ($($field:ident),*) => {
+            $(
+                if base.is_none_or(|base| base.$field != local.$field) {
+                    merged.$field = local.$field.clone();
+                }
+            )*
+        };
+    }
+    take_local!(summary, due, priority, categories, parent);
+    // Completion is one change
+    if base.is_none_or(|base| (base.completed, base.completed_at) != (local.completed, local.completed_at)) {
+        merged.completed = local.completed;
+        merged.completed_at = local.completed_at;
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::caldav::test_server::TestServer;
+    use crate::caldav::{Credentials, Error};
+
+    fn client(server: &TestServer) -> Client {
+        Client::new(&server.collection_url(), Some(&TestServer::credentials())).unwrap()
+    }
+
+    /// The local list after applying `report` to `local`, as the apps do.
+    fn apply(local: &[LocalTask], report: &Report) -> Vec<LocalTask> {
+        let mut tasks = local.to_vec();
+        for (todo, remote) in &report.synced {
+            let task = LocalTask {
+                todo: todo.clone(),
+                remote: Some(remote.clone()),
+                base: Some(todo.clone()),
+                dirty: false,
+            };
+            match tasks.iter_mut().find(|t| t.todo.uid == todo.uid) {
+                Some(existing) => *existing = task,
+                None => tasks.push(task),
+            }
+        }
+        tasks.retain(|t| !report.removed.contains(&t.todo.uid));
+        tasks
+    }
+
+    fn new_task(uid: &str, summary: &str) -> LocalTask {
+        LocalTask { todo: VTodo::new(uid, summary), remote: None, base: None, dirty: true }
+    }
+
+    fn edit(tasks: &mut [LocalTask], uid: &str, f: impl FnOnce(&mut VTodo)) {
+        let task = tasks.iter_mut().find(|t| t.todo.uid == uid).unwrap();
+        f(&mut task.todo);
+        task.dirty = true;
+    }
+
+    fn summaries(tasks: &[LocalTask]) -> Vec<&str> {
+        let mut summaries = tasks.iter().map(|t| t.todo.summary.as_str()).collect::<Vec<_>>();
+        summaries.sort();
+        summaries
+    }
+
+    #[test]
+    fn first_sync_uploads_and_downloads() {
+        let server = TestServer::start();
+        server.insert(VTodo::new("theirs", "Call mum"));
+        let local = vec![new_task("ours", "Buy milk")];
+
+        let report = sync(&client(&server), &local, &[]).unwrap();
+        assert_eq!((report.uploaded, report.downloaded), (1, 1));
+        let local = apply(&local, &report);
+        assert_eq!(summaries(&local), ["Buy milk", "Call mum"]);
+        assert_eq!(server.summaries(), ["Buy milk", "Call mum"]);
+
+        // Nothing left to do
+        assert_eq!(sync(&client(&server), &local, &[]).unwrap(), Report::default());
+    }
+
+    #[test]
+    fn pulls_remote_edits_and_deletions() {
+        let server = TestServer::start();
+        let local = vec![new_task("a", "Buy milk"), new_task("b", "Call mum")];
+        let local = apply(&local, &sync(&client(&server), &local, &[]).unwrap());
+
+        server.edit("a", |todo| todo.completed = true);
+        server.remove("b");
+        let report = sync(&client(&server), &local, &[]).unwrap();
+        assert_eq!((report.downloaded, report.deleted, report.uploaded), (1, 1, 0));
+        let local = apply(&local, &report);
+        assert_eq!(local.len(), 1);
+        assert!(local[0].todo.completed);
+    }
+
+    #[test]
+    fn pushes_local_edits_and_deletions() {
+        let server = TestServer::start();
+        let local = vec![new_task("a", "Buy milk"), new_task("b", "Call mum")];
+        let mut local = apply(&local, &sync(&client(&server), &local, &[]).unwrap());
+
+        edit(&mut local, "a", |todo| todo.summary = "Buy oat milk".to_string());
+        let removed = local.remove(1);
+        let remote = removed.remote.unwrap();
+        let tombstone = Tombstone { uid: removed.todo.uid, href: remote.href, etag: remote.etag };
+
+        let report = sync(&client(&server), &local, &[tombstone]).unwrap();
+        assert_eq!((report.uploaded, report.deleted), (1, 1));
+        assert_eq!(server.summaries(), ["Buy oat milk"]);
+    }
+
+    #[test]
+    fn merges_edits_made_on_both_sides() {
+        let server = TestServer::start();
+        let local = vec![new_task("a", "Buy milk")];
+        let mut local = apply(&local, &sync(&client(&server), &local, &[]).unwrap());
+
+        server.edit("a", |todo| {
+            todo.priority = Some(1);
+            todo.summary = "Buy milk today".to_string();
+        });
+        edit(&mut local, "a", |todo| todo.summary = "Buy oat milk".to_string());
+
+        let report = sync(&client(&server), &local, &[]).unwrap();
+        assert_eq!(report.merged, 1);
+        let local = apply(&local, &report);
+        // The summary changed on both sides and the local one wins, the priority is kept
+        assert_eq!(local[0].todo.summary, "Buy oat milk");
+        assert_eq!(local[0].todo.priority, Some(1));
+        assert_eq!(server.todo("a").unwrap(), local[0].todo);
+    }
+
+    #[test]
+    fn edits_on_the_server_outlive_local_deletion() {
+        let server = TestServer::start();
+        let local = vec![new_task("a", "Buy milk")];
+        let local = apply(&local, &sync(&client(&server), &local, &[]).unwrap());
+        let remote = local[0].remote.clone().unwrap();
+        let tombstone = Tombstone { uid: "a".to_string(), href: remote.href, etag: remote.etag };
+
+        server.edit("a", |todo| todo.summary = "Buy milk and eggs".to_string());
+        let report = sync(&client(&server), &[], &[tombstone]).unwrap();
+        assert_eq!((report.deleted, report.downloaded), (0, 1));
+        assert_eq!(summaries(&apply(&[], &report)), ["Buy milk and eggs"]);
+    }
+
+    #[test]
+    fn wrong_credentials_are_refused() {
+        let server = TestServer::start();
+        let credentials = Credentials { password: "guess".to_string(), ..TestServer::credentials() };
+        for credentials in [None, Some(&credentials)] {
+            let client = Client::new(&server.collection_url(), credentials).unwrap();
+            assert!(matches!(client.list(), Err(Error::Status(401))));
+        }
+    }
+
+    #[test]
+    fn stale_etags_are_refused() {
+        let server = TestServer::start();
+        let client = client(&server);
+        let href = client.href_for("a");
+        let etag = client.put(&href, &VTodo::new("a", "Buy milk"), None).unwrap();
+        assert!(etag.is_some());
+
+        server.edit("a", |todo| todo.summary = "Buy bread".to_string());
+        assert!(matches!(client.put(&href, &VTodo::new("a", "Buy milk"), etag.as_deref()), Err(Error::PreconditionFailed)));
+        assert!(matches!(client.delete(&href, etag.as_deref()), Err(Error::PreconditionFailed)));
+        assert!(matches!(client.put(&href, &VTodo::new("a", "Buy milk"), None), Err(Error::PreconditionFailed)));
+    }
+}
@@ -0,0 +1,171 @@
+//! An in-memory stand-in for a CalDAV server, serving one collection on a local port.
+//!
+//! It understands just what [`Client`](super::Client) sends: the calendar query, and PUT and
+//! DELETE with their ETag preconditions.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::NaiveDateTime;
+
+use super::Credentials;
+use crate::ical::{self, VTodo};
+
+const COLLECTION: &str = "/tasks/";
+
+#[derive(Default)]
+struct Collection {
+    /// ETag and todo by path
+    resources: BTreeMap<String, (String, VTodo)>,
+    versions: u32,
+}
+
+impl Collection {
+    fn store(&mut self, path: String, todo: VTodo) -> String {
+        self.versions += 1;
+        let etag = format!("\"{}\"", self.versions);
+        self.resources.insert(path, (etag.clone(), todo));
+        etag
+    }
+
+    fn path_of(&self, uid: &str) -> Option<String> {
+        self.resources.iter().find(|(_, (_, todo))| todo.uid == uid).map(|(path, _)| path.clone())
+    }
+}
+
+pub struct TestServer {
+    port: u16,
+    collection: Arc<Mutex<Collection>>,
+}
+
+impl TestServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let collection = Arc::new(Mutex::new(Collection::default()));
+        let shared = collection.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond(stream, &shared);
+            }
+        });
+        Self { port, collection }
+    }
+
+    pub fn collection_url(&self) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, COLLECTION)
+    }
+
+    /// The only login the server accepts.
+    pub fn credentials() -> Credentials {
+        Credentials { user: "user".to_string(), password: "secret".to_string() }
+    }
+
+    /// Adds a todo as another client would.
+    pub fn insert(&self, todo: VTodo) {
+        let path = format!("{}{}.ics", COLLECTION, todo.uid);
+        self.collection.lock().unwrap().store(path, todo);
+    }
+
+    pub fn edit(&self, uid: &str, f: impl FnOnce(&mut VTodo)) {
+        let mut collection = self.collection.lock().unwrap();
+        let path = collection.path_of(uid).unwrap();
+        let mut todo = collection.resources[&path].1.clone();
+        f(&mut todo);
+        collection.store(path, todo);
+    }
+
+    pub fn remove(&self, uid: &str) {
+        let mut collection = self.collection.lock().unwrap();
+        let path = collection.path_of(uid).unwrap();
+        collection.resources.remove(&path);
+    }
+
+    pub fn todo(&self, uid: &str) -> Option<VTodo> {
+        let collection = self.collection.lock().unwrap();
+        collection.resources.values().find(|(_, todo)| todo.uid == uid).map(|(_, todo)| todo.clone())
+    }
+
+    pub fn summaries(&self) -> Vec<String> {
+        let collection = self.collection.lock().unwrap();
+        let mut summaries = collection.resources.values().map(|(_, todo)| todo.summary.clone()).collect::<Vec<_>>();
+        summaries.sort();
+        summaries
+    }
+}
+
+fn respond(stream: TcpStream, collection: &Mutex<Collection>) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let Some((name, value)) = line.trim_end().split_once(':') else { break };
+        headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+    }
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+    let length = header("content-length").and_then(|len| len.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut collection = collection.lock().unwrap();
+    let current = collection.resources.get(&path).map(|(etag, _)| etag.clone());
+    let precondition_holds = match (header("if-match"), header("if-none-match"), &current) {
+        (Some(expected), _, Some(etag)) => expected == etag,
+        (Some(_), _, None) => false,
+        (_, Some("*"), current) => current.is_none(),
+        _ => true,
+    };
+
+    let (status, etag, body) = match method.as_str() {
+        _ if header("authorization") != Some("Basic dXNlcjpzZWNyZXQ=") => ("401 Unauthorized", None, String::new()),
+        "REPORT" if path == COLLECTION => ("207 Multi-Status", None, multistatus(&collection)),
+        _ if !precondition_holds => ("412 Precondition Failed", None, String::new()),
+        "PUT" => match ical::parse(&String::from_utf8_lossy(&body)).into_iter().next() {
+            Some(todo) => ("201 Created", Some(collection.store(path, todo)), String::new()),
+            None => ("400 Bad Request", None, String::new()),
+        },
+        "DELETE" => match collection.resources.remove(&path) {
+            Some(_) => ("204 No Content", None, String::new()),
+            None => ("404 Not Found", None, String::new()),
+        },
+        _ => ("405 Method Not Allowed", None, String::new()),
+    };
+
+    // One request per connection
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: {}\r\n", status, body.len());
+    if let Some(etag) = etag {
+        response.push_str(&format!("ETag: {}\r\n", etag));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    (&stream).write_all(response.as_bytes()).unwrap();
+}
+
+fn multistatus(collection: &Collection) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">"#);
+    for (path, (etag, todo)) in &collection.resources {
+        let data = ical::write([todo], NaiveDateTime::default());
+        xml.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag>\
+             <c:calendar-data>{}</c:calendar-data></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            path,
+            escape(etag),
+            escape(&data)
+        ));
+    }
+    xml.push_str("</d:multistatus>");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\r', "&#13;")
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Completable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    #[default]
+    All,
+    Active,
+    Completed,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::All, Filter::Active, Filter::Completed];
+
+    pub fn matches<T: Completable + ?Sized>(self, task: &T) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Active => !task.is_completed(),
+            Filter::Completed => task.is_completed(),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Filter::All => "All",
+            Filter::Active => "Active",
+            Filter::Completed => "Completed",
+        }
+    }
+}
+
+/// Parses `all`, `active` or `completed`, ignoring case.
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Filter::ALL.into_iter()
+            .find(|filter| filter.label().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseFilterError(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFilterError(String);
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown filter `{}`, expected all, active or completed", self.0)
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+
+    #[test]
+    fn matches_by_completion() {
+        let open = Task::new("open");
+        let mut done = Task::new("done");
+        done.completed = true;
+
+        assert!(Filter::All.matches(&open) && Filter::All.matches(&done));
+        assert!(Filter::Active.matches(&open) && !Filter::Active.matches(&done));
+        assert!(!Filter::Completed.matches(&open) && Filter::Completed.matches(&done));
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!("active".parse(), Ok(Filter::Active));
+        assert_eq!("Completed".parse(), Ok(Filter::Completed));
+        assert!("done".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,433 @@
+//! Reader and writer for the `VTODO` components of [iCalendar](https://www.rfc-editor.org/rfc/rfc5545) files.
+//!
+//! Only the properties the apps use are parsed out. Every other property of a todo, nested
+//! components such as alarms included, is kept as its original content line and written
+//! back unchanged, so passing a file through an app doesn't lose what a calendar client put
+//! in it. Components other than `VTODO` are skipped.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+const DATE_FORMAT: &str = "%Y%m%d";
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+const PRODID: &str = "-//todomvc-rs//todo_core//EN";
+/// Longest content line in bytes, not counting the line break. Longer ones are folded.
+const LINE_LIMIT: usize = 75;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VTodo {
+    /// Identifies the todo for calendar clients, so it must not change between exports.
+    pub uid: String,
+    pub summary: String,
+    pub completed: bool,
+    /// When the todo was completed, in UTC.
+    pub completed_at: Option<NaiveDateTime>,
+    /// Just the day, even if the file has a due time.
+    pub due: Option<NaiveDate>,
+    /// `1` is the most important and `9` the least.
+    pub priority: Option<u8>,
+    pub categories: Vec<String>,
+    /// UID of the todo this one is a subtask of.
+    pub parent: Option<String>,
+    /// Unfolded content lines of every other property, in their original order.
+    pub other: Vec<String>,
+    // The lines `due` and a status other than completed or needs-action were read from,
+    // written back as they were while they still hold
+    due_line: Option<String>,
+    status_line: Option<String>,
+}
+
+impl VTodo {
+    pub fn new(uid: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            uid: uid.into(),
+            summary: summary.into(),
+            ..Self::default()
+        }
+    }
+
+    fn set_property(&mut self, name: &str, value: &str, line: &str) {
+        let parsed = match name {
+            "UID" => {
+                self.uid = unescape(value);
+                true
+            }
+            "SUMMARY" => {
+                self.summary = unescape(value);
+                true
+            }
+            "STATUS" => {
+                self.completed = value.eq_ignore_ascii_case("COMPLETED");
+                if !self.completed && !value.eq_ignore_ascii_case("NEEDS-ACTION") {
+                    self.status_line = Some(line.to_string());
+                }
+                true
+            }
+            "COMPLETED" => {
+                self.completed_at = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), DATE_TIME_FORMAT).ok();
+                self.completed_at.is_some()
+            }
+            "DUE" => {
+                self.due = value.get(..8).and_then(|date| NaiveDate::parse_from_str(date, DATE_FORMAT).ok());
+                self.due_line = Some(line.to_string());
+                self.due.is_some()
+            }
+            "PRIORITY" => {
+                // 0 means no priority at all
+                self.priority = value.parse().ok().filter(|priority| (1..=9).contains(priority));
+                self.priority.is_some() || value == "0"
+            }
+            "CATEGORIES" => {
+                self.categories.extend(split_list(value).iter().map(|category| unescape(category)));
+                true
+            }
+            // Siblings and children are left alone, the parent link is all that's needed
+            "RELATED-TO" if parameter(line, "RELTYPE").is_none_or(|kind| kind.eq_ignore_ascii_case("PARENT")) => {
+                self.parent = Some(unescape(value));
+                true
+            }
+            _ => false,
+        };
+        // Whatever couldn't be understood is still passed on
+        if !parsed {
+            self.other.push(line.to_string());
+        }
+    }
+
+    fn write_to(&self, out: &mut String, stamp: NaiveDateTime) {
+        push_line(out, "BEGIN:VTODO");
+        push_line(out, &format!("UID:{}", escape(&self.uid)));
+        // Required. One that came with the todo is kept as it was
+        let is_stamp = |line: &&String| property_name(line).as_deref() == Some("DTSTAMP");
+        match self.other.iter().find(is_stamp) {
+            Some(line) => push_line(out, line),
+            None => push_line(out, &format!("DTSTAMP:{}Z", stamp.format(DATE_TIME_FORMAT))),
+        }
+        push_line(out, &format!("SUMMARY:{}", escape(&self.summary)));
+
+        match &self.status_line {
+            _ if self.completed => push_line(out, "STATUS:COMPLETED"),
+            Some(line) => push_line(out, line),
+            None => push_line(out, "STATUS:NEEDS-ACTION"),
+        }
+        if let (true, Some(completed_at)) = (self.completed, self.completed_at) {
+            push_line(out, &format!("COMPLETED:{}Z", completed_at.format(DATE_TIME_FORMAT)));
+        }
+
+        if let Some(due) = self.due {
+            let unchanged = self.due_line.as_ref().filter(|line| {
+                let value = line.rsplit(':').next().unwrap_or_default();
+                value.starts_with(&due.format(DATE_FORMAT).to_string())
+            });
+            match unchanged {
+                Some(line) => push_line(out, line),
+                None => push_line(out, &format!("DUE;VALUE=DATE:{}", due.format(DATE_FORMAT))),
+            }
+        }
+        if let Some(priority) = self.priority {
+            push_line(out, &format!("PRIORITY:{}", priority));
+        }
+        if !self.categories.is_empty() {
+            let categories = self.categories.iter().map(|category| escape(category)).collect::<Vec<_>>();
+            push_line(out, &format!("CATEGORIES:{}", categories.join(",")));
+        }
+        if let Some(parent) = &self.parent {
+            push_line(out, &format!("RELATED-TO;RELTYPE=PARENT:{}", escape(parent)));
+        }
+
+        for line in self.other.iter().filter(|line| !is_stamp(line)) {
+            push_line(out, line);
+        }
+        push_line(out, "END:VTODO");
+    }
+}
+
+/// Reads every `VTODO` in a calendar file. Lines that aren't valid content lines are skipped.
+pub fn parse(text: &str) -> Vec<VTodo> {
+    let mut todos = Vec::new();
+    let mut current: Option<VTodo> = None;
+    let mut status_seen = false;
+    // Components inside the current todo, such as a `VALARM`, which are kept whole
+    let mut nested = Vec::new();
+
+    for line in unfold(text) {
+        let Some((name, value)) = split_line(&line) else { continue };
+
+        let Some(todo) = current.as_mut() else {
+            if name == "BEGIN" && value.eq_ignore_ascii_case("VTODO") {
+                current = Some(VTodo::default());
+                status_seen = false;
+            }
+            continue;
+        };
+
+        if !nested.is_empty() || name == "BEGIN" {
+            todo.other.push(line.to_string());
+            match name.as_str() {
+                "BEGIN" => nested.push(value.to_ascii_uppercase()),
+                "END" if nested.last().map(String::as_str) == Some(&value.to_ascii_uppercase()) => {
+                    nested.pop();
+                }
+                _ => {}
+            }
+        } else if name == "END" {
+            if let Some(mut todo) = current.take() {
+                // Some clients only record when a todo was done
+                if !status_seen && todo.completed_at.is_some() {
+                    todo.completed = true;
+                }
+                todos.push(todo);
+            }
+        } else {
+            status_seen |= name == "STATUS";
+            todo.set_property(&name, value, &line);
+        }
+    }
+    todos
+}
+
+/// A whole calendar file with the given todos, using CRLF line breaks as the format requires.
+///
+/// `stamp` is written as the `DTSTAMP` of todos that don't have one yet.
+pub fn write<'a>(todos: impl IntoIterator<Item = &'a VTodo>, stamp: NaiveDateTime) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, &format!("PRODID:{}", PRODID));
+    for todo in todos {
+        todo.write_to(&mut out, stamp);
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Joins folded lines back together and drops blank ones.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// The uppercased property name and the value of a content line. Parameters are left out,
+/// the line itself keeps them.
+fn split_line(line: &str) -> Option<(String, &str)> {
+    // The value starts at the first colon that isn't inside a quoted parameter value
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let name = property_name(line)?;
+    Some((name, &line[colon + 1..]))
+}
+
+fn property_name(line: &str) -> Option<String> {
+    let end = line.find([';', ':'])?;
+    Some(line[..end].to_ascii_uppercase()).filter(|name| !name.is_empty())
+}
+
+/// The value of parameter `name` of a content line, e.g. `RELTYPE` in `RELATED-TO;RELTYPE=PARENT:…`.
+/// Quoted values aren't needed for the parameters read here.
+fn parameter<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let head = &line[..line.find(':')?];
+    head.split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+/// Splits a list value at the commas that aren't escaped.
+fn split_list(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(next) => unescaped.push(next),
+                None => unescaped.push('\\'),
+            },
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Adds a content line, folding it so no line is longer than [`LINE_LIMIT`] bytes.
+fn push_line(out: &mut String, line: &str) {
+    let mut rest = line;
+    // Continuation lines start with a space, which counts towards the limit
+    let mut limit = LINE_LIMIT;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        out.push_str(&rest[..split]);
+        out.push_str("\r\n ");
+        rest = &rest[split..];
+        limit = LINE_LIMIT - 1;
+    }
+    out.push_str(rest);
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "\
+BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Example//Tasks//EN\r
+BEGIN:VEVENT\r
+UID:event-1\r
+SUMMARY:Not a todo\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:todo-1@example.com\r
+DTSTAMP:20240501T090000Z\r
+SUMMARY:Buy milk\\, eggs and a very long list of other things that needs fol\r
+ ding\r
+DUE;TZID=Europe/Berlin:20240506T170000\r
+PRIORITY:1\r
+CATEGORIES:Errands,Home\r
+RELATED-TO;RELTYPE=SIBLING:todo-3@example.com\r
+X-APPLE-SORT-ORDER:42\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT15M\r
+END:VALARM\r
+END:VTODO\r
+BEGIN:VTODO\r
+UID:todo-2@example.com\r
+SUMMARY:Pay rent\r
+RELATED-TO:todo-1@example.com\r
+COMPLETED:20240502T101500Z\r
+END:VTODO\r
+BEGIN:VTODO\r
+UID:todo-3@example.com\r
+SUMMARY:Paint fence\r
+STATUS:IN-PROCESS\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+    fn stamp() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_the_known_properties() {
+        let todos = parse(CALENDAR);
+        assert_eq!(todos.len(), 3);
+
+        let milk = &todos[0];
+        assert_eq!(milk.uid, "todo-1@example.com");
+        assert_eq!(milk.summary, "Buy milk, eggs and a very long list of other things that needs folding");
+        assert!(!milk.completed);
+        assert_eq!(milk.due, NaiveDate::from_ymd_opt(2024, 5, 6));
+        assert_eq!(milk.priority, Some(1));
+        assert_eq!(milk.categories, ["Errands", "Home"]);
+        assert_eq!(milk.parent, None);
+        assert_eq!(milk.other, [
+            "DTSTAMP:20240501T090000Z",
+            "RELATED-TO;RELTYPE=SIBLING:todo-3@example.com",
+            "X-APPLE-SORT-ORDER:42",
+            "BEGIN:VALARM",
+            "ACTION:DISPLAY",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+        ]);
+
+        // No status, but a completion time
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].parent.as_deref(), Some("todo-1@example.com"));
+        assert!(!todos[2].completed);
+    }
+
+    #[test]
+    fn round_trips_what_it_does_not_understand() {
+        let written = write(&parse(CALENDAR), stamp());
+        // Todos without a DTSTAMP get one the first time around
+        assert_eq!(write(&parse(&written), stamp()), written);
+
+        assert!(written.contains("DUE;TZID=Europe/Berlin:20240506T170000\r\n"));
+        assert!(written.contains("STATUS:IN-PROCESS\r\n"));
+        assert!(written.contains("BEGIN:VALARM\r\nACTION:DISPLAY\r\nTRIGGER:-PT15M\r\nEND:VALARM\r\n"));
+        assert!(written.contains("DTSTAMP:20240501T090000Z\r\n"));
+        assert_eq!(written.matches("DTSTAMP").count(), 3);
+        assert!(!written.contains("VEVENT"));
+    }
+
+    #[test]
+    fn writes_changes() {
+        let mut todos = parse(CALENDAR);
+        todos[0].due = NaiveDate::from_ymd_opt(2024, 5, 7);
+        todos[0].completed = true;
+        todos[0].completed_at = Some(stamp());
+        todos[2].completed = true;
+
+        let written = write(&todos, stamp());
+        assert!(written.contains("DUE;VALUE=DATE:20240507\r\n"));
+        assert!(written.contains("COMPLETED:20240601T120000Z\r\n"));
+        assert!(!written.contains("IN-PROCESS"));
+        assert_eq!(write(&parse(&written), stamp()), written);
+    }
+
+    #[test]
+    fn escapes_and_folds_long_lines() {
+        let summary = "Ünïcödé; with, commas\\ and a line\nbreak ".repeat(5);
+        let todo = VTodo::new("uid-1", summary.clone());
+        let written = write([&todo], stamp());
+
+        assert!(written.split("\r\n").all(|line| line.len() <= LINE_LIMIT));
+        assert!(written.contains("DTSTAMP:20240601T120000Z\r\n"));
+        assert_eq!(parse(&written)[0].summary, summary);
+    }
+}
@@ -0,0 +1,103 @@
+//! The JSON task file written by the druid app and the `todo` CLI.
+//!
+//! The file is a plain array of tasks. Fields added later are optional, so older files
+//! keep loading.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Completable;
+
+/// Environment variable that points every app at a different task file.
+pub const FILE_ENV: &str = "TODO_FILE";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredTask {
+    pub id: Uuid,
+    pub description: String,
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<char>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_on: Option<NaiveDate>,
+}
+
+impl StoredTask {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            description: description.into(),
+            completed: false,
+            priority: None,
+            created: None,
+            completed_on: None,
+        }
+    }
+}
+
+impl Completable for StoredTask {
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
+}
+
+/// `$TODO_FILE` if set, otherwise the druid app's list in the user's data directory.
+pub fn default_path() -> Option<PathBuf> {
+    match std::env::var_os(FILE_ENV) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => dirs::data_dir().map(|dir| dir.join("druid-todo").join("todos.json")),
+    }
+}
+
+/// Reads a task file, treating a missing file as an empty list.
+pub fn load(path: &Path) -> io::Result<Vec<StoredTask>> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub fn save(path: &Path, tasks: &[StoredTask]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write next to the target and rename, so a crash never leaves a half-written list
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(tasks)?)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optional_fields_may_be_missing() {
+        let json = r#"[
+            {"id": "67e55044-10b1-426f-9247-bb680e5fe0c8", "description": "Buy milk", "completed": false},
+            {"id": "9b2e3c1a-8c4e-4f0a-a7d4-1f6c2b3d4e5f", "description": "Pay rent", "completed": true,
+             "priority": "A", "created": "2024-01-01", "completed_on": "2024-01-05"}
+        ]"#;
+        let tasks: Vec<StoredTask> = serde_json::from_str(json).unwrap();
+        assert_eq!(tasks[0].priority, None);
+        assert_eq!(tasks[1].priority, Some('A'));
+        assert_eq!(tasks[1].completed_on, NaiveDate::from_ymd_opt(2024, 1, 5));
+
+        let written = serde_json::to_string(&tasks[0]).unwrap();
+        assert!(!written.contains("priority"));
+        assert_eq!(serde_json::from_str::<StoredTask>(&written).unwrap(), tasks[0]);
+    }
+}
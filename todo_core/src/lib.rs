@@ -0,0 +1,78 @@
+//! Framework-agnostic todo model shared by every frontend.
+//!
+//! Each GUI keeps its own widget-bound item type and implements [`Completable`] for it,
+//! so filtering, toggle-all, clear-completed and counting behave the same everywhere.
+
+#[cfg(feature = "caldav")]
+pub mod caldav;
+mod filter;
+pub mod ical;
+#[cfg(feature = "json")]
+pub mod json;
+mod list;
+mod recurrence;
+mod task;
+pub mod todotxt;
+
+pub use filter::{Filter, ParseFilterError};
+pub use list::TaskList;
+pub use recurrence::{ParseRecurrenceError, Recurrence};
+pub use task::{Completable, Task};
+
+/// Number of tasks that are not completed yet.
+pub fn count_active<'a, T: Completable + 'a>(tasks: impl IntoIterator<Item = &'a T>) -> usize {
+    tasks.into_iter().filter(|task| !task.is_completed()).count()
+}
+
+/// Whether there is anything for "Clear Complete" to remove.
+pub fn any_completed<'a, T: Completable + 'a>(tasks: impl IntoIterator<Item = &'a T>) -> bool {
+    tasks.into_iter().any(|task| task.is_completed())
+}
+
+/// Completes every task, or reopens all of them if they are all completed already.
+pub fn toggle_all<'a, T: Completable + 'a>(tasks: impl IntoIterator<Item = &'a mut T>) {
+    let tasks = tasks.into_iter().collect::<Vec<_>>();
+    let check = tasks.iter().any(|task| !task.is_completed());
+    for task in tasks {
+        task.set_completed(check);
+    }
+}
+
+/// "task" or "tasks", depending on the count.
+pub fn task_noun(count: usize) -> &'static str {
+    if count == 1 { "task" } else { "tasks" }
+}
+
+/// The "N task(s)" label shown in every footer.
+pub fn tasks_left_label(count: usize) -> String {
+    format!("{} {}", count, task_noun(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_all_completes_then_reopens() {
+        let mut tasks = [Task::new("a"), Task::new("b")];
+        tasks[0].completed = true;
+
+        toggle_all(tasks.iter_mut());
+        assert!(tasks.iter().all(|task| task.completed));
+
+        toggle_all(tasks.iter_mut());
+        assert!(tasks.iter().all(|task| !task.completed));
+    }
+
+    #[test]
+    fn counts_and_labels() {
+        let mut tasks = [Task::new("a"), Task::new("b"), Task::new("c")];
+        assert!(!any_completed(&tasks));
+        assert_eq!(tasks_left_label(count_active(&tasks)), "3 tasks");
+
+        tasks[0].completed = true;
+        tasks[1].completed = true;
+        assert!(any_completed(&tasks));
+        assert_eq!(tasks_left_label(count_active(&tasks)), "1 task");
+    }
+}
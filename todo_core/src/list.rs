@@ -0,0 +1,126 @@
+use crate::{Filter, Task};
+
+/// An ordered list of tasks, for frontends that don't need their own item type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskList {
+    tasks: Vec<Task>,
+}
+
+impl TaskList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Task> {
+        self.tasks.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Task> {
+        self.tasks.get_mut(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter()
+    }
+
+    /// Tasks matching `filter`, along with their position in the list.
+    pub fn filtered(&self, filter: Filter) -> impl Iterator<Item = (usize, &Task)> {
+        self.tasks.iter()
+            .enumerate()
+            .filter(move |(_, task)| filter.matches(*task))
+    }
+
+    /// Adds a task with the given description, ignoring blank input.
+    pub fn add(&mut self, description: &str) -> Option<usize> {
+        let description = description.trim();
+        if description.is_empty() {
+            return None;
+        }
+        self.tasks.push(Task::new(description));
+        Some(self.tasks.len() - 1)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Task> {
+        (index < self.tasks.len()).then(|| self.tasks.remove(index))
+    }
+
+    pub fn toggle_all(&mut self) {
+        crate::toggle_all(self.tasks.iter_mut());
+    }
+
+    pub fn clear_completed(&mut self) {
+        self.tasks.retain(|task| Filter::Active.matches(task));
+    }
+
+    pub fn active_count(&self) -> usize {
+        crate::count_active(&self.tasks)
+    }
+
+    pub fn has_completed(&self) -> bool {
+        crate::any_completed(&self.tasks)
+    }
+}
+
+impl From<Vec<Task>> for TaskList {
+    fn from(tasks: Vec<Task>) -> Self {
+        Self { tasks }
+    }
+}
+
+impl From<TaskList> for Vec<Task> {
+    fn from(list: TaskList) -> Self {
+        list.tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_ignores_blank_descriptions() {
+        let mut list = TaskList::new();
+        assert_eq!(list.add("  "), None);
+        assert_eq!(list.add(" milk "), Some(0));
+        assert_eq!(list.get(0).unwrap().description, "milk");
+    }
+
+    #[test]
+    fn clear_completed_keeps_order_of_active() {
+        let mut list = TaskList::new();
+        for name in ["a", "b", "c", "d"] {
+            list.add(name);
+        }
+        list.get_mut(1).unwrap().completed = true;
+        list.get_mut(3).unwrap().completed = true;
+
+        list.clear_completed();
+
+        let names = list.iter().map(|task| task.description.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "c"]);
+        assert!(!list.has_completed());
+    }
+
+    #[test]
+    fn filtered_reports_list_positions() {
+        let mut list = TaskList::new();
+        for name in ["a", "b", "c"] {
+            list.add(name);
+        }
+        list.get_mut(1).unwrap().completed = true;
+
+        let active = list.filtered(Filter::Active).map(|(i, _)| i).collect::<Vec<_>>();
+        let completed = list.filtered(Filter::Completed).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(active, [0, 2]);
+        assert_eq!(completed, [1]);
+        assert_eq!(list.active_count(), 2);
+    }
+}
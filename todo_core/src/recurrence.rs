@@ -0,0 +1,199 @@
+//! Repeating schedules, written as a small subset of iCalendar `RRULE`s.
+//!
+//! `FREQ=DAILY`, `FREQ=WEEKLY;BYDAY=MO,TH` and `FREQ=MONTHLY;BYMONTHDAY=15` repeat on the
+//! calendar. `FREQ=DAILY;INTERVAL=3;X-FROM=COMPLETION` has no standard equivalent: the next
+//! occurrence is due three days after the last one was done, however late that was.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "MO"),
+    (Weekday::Tue, "TU"),
+    (Weekday::Wed, "WE"),
+    (Weekday::Thu, "TH"),
+    (Weekday::Fri, "FR"),
+    (Weekday::Sat, "SA"),
+    (Weekday::Sun, "SU"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    /// On these days of the week, Monday first.
+    Weekly(Vec<Weekday>),
+    /// On this day of the month, or the last day of months that are too short.
+    Monthly(u32),
+    /// This many days after the previous occurrence was completed.
+    AfterCompletion(u32),
+}
+
+impl Recurrence {
+    /// When the occurrence after one that was `due` and got done on `done_on` is due.
+    ///
+    /// Calendar schedules move on from the due date, or from `done_on` if that's later, so
+    /// catching up on an overdue chore doesn't leave another overdue one behind.
+    pub fn next_due(&self, due: Option<NaiveDate>, done_on: NaiveDate) -> NaiveDate {
+        let from = due.map_or(done_on, |due| due.max(done_on));
+        match self {
+            Recurrence::Daily => add_days(from, 1),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|n| add_days(from, n))
+                .find(|date| days.contains(&date.weekday()))
+                .unwrap_or_else(|| add_days(from, 7)),
+            Recurrence::Monthly(day) => {
+                let this_month = day_of_month(from, *day);
+                if this_month > from {
+                    this_month
+                } else {
+                    let next_month = from.with_day(1).and_then(|first| first.checked_add_months(Months::new(1)));
+                    next_month.map_or(NaiveDate::MAX, |first| day_of_month(first, *day))
+                }
+            }
+            Recurrence::AfterCompletion(days) => add_days(done_on, *days),
+        }
+    }
+}
+
+fn add_days(date: NaiveDate, days: u32) -> NaiveDate {
+    date.checked_add_days(Days::new(days.into())).unwrap_or(NaiveDate::MAX)
+}
+
+/// `day` in the month of `date`, moved back to the month's last day if there's no such day.
+fn day_of_month(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day).rev()
+        .find_map(|day| date.with_day(day))
+        .unwrap_or(date)
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => f.write_str("FREQ=DAILY"),
+            Recurrence::Weekly(days) => {
+                f.write_str("FREQ=WEEKLY;BYDAY=")?;
+                let codes = days.iter()
+                    .filter_map(|day| WEEKDAYS.iter().find(|(weekday, _)| weekday == day))
+                    .map(|(_, code)| *code)
+                    .collect::<Vec<_>>();
+                f.write_str(&codes.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "FREQ=MONTHLY;BYMONTHDAY={}", day),
+            Recurrence::AfterCompletion(days) => write!(f, "FREQ=DAILY;INTERVAL={};X-FROM=COMPLETION", days),
+        }
+    }
+}
+
+/// Parses the rules written by `Display`, ignoring case and an `RRULE:` prefix.
+impl FromStr for Recurrence {
+    type Err = ParseRecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseRecurrenceError(s.to_string());
+
+        let rule = s.trim().to_ascii_uppercase();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(&rule);
+
+        let (mut freq, mut interval, mut by_day, mut by_month_day, mut from_completion) = (None, 1, None, None, false);
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(err)?;
+            match key {
+                "FREQ" => freq = Some(value),
+                "INTERVAL" => interval = value.parse().ok().filter(|n| *n > 0).ok_or_else(err)?,
+                "BYDAY" => by_day = Some(value),
+                "BYMONTHDAY" => by_month_day = Some(value.parse().ok().filter(|day| (1..=31).contains(day)).ok_or_else(err)?),
+                "X-FROM" if value == "COMPLETION" => from_completion = true,
+                _ => return Err(err()),
+            }
+        }
+
+        match (freq, by_day, by_month_day) {
+            (Some("DAILY"), None, None) if from_completion => Ok(Recurrence::AfterCompletion(interval)),
+            _ if from_completion || interval != 1 => Err(err()),
+            (Some("DAILY"), None, None) => Ok(Recurrence::Daily),
+            (Some("WEEKLY"), Some(codes), None) => {
+                let mut days = codes.split(',')
+                    .map(|code| WEEKDAYS.iter().find(|(_, c)| *c == code).map(|(day, _)| *day))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(err)?;
+                days.sort_by_key(|day| day.num_days_from_monday());
+                days.dedup();
+                Ok(Recurrence::Weekly(days))
+            }
+            (Some("MONTHLY"), None, Some(day)) => Ok(Recurrence::Monthly(day)),
+            _ => Err(err()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecurrenceError(String);
+
+impl fmt::Display for ParseRecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported recurrence rule `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseRecurrenceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn rules_round_trip() {
+        let rules = [
+            Recurrence::Daily,
+            Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]),
+            Recurrence::Monthly(31),
+            Recurrence::AfterCompletion(3),
+        ];
+        for rule in rules {
+            assert_eq!(rule.to_string().parse(), Ok(rule));
+        }
+        assert_eq!("rrule:freq=weekly;byday=th,mo,th".parse(), Ok(Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu])));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_schedule() {
+        for rule in ["", "FREQ=YEARLY", "FREQ=WEEKLY", "FREQ=WEEKLY;BYDAY=XX", "FREQ=DAILY;INTERVAL=2", "FREQ=MONTHLY;BYMONTHDAY=32"] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn calendar_rules_move_on_from_the_due_date() {
+        // 2024-05-06 is a Monday
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]);
+        assert_eq!(weekly.next_due(Some(date(2024, 5, 6)), date(2024, 5, 4)), date(2024, 5, 9));
+        assert_eq!(weekly.next_due(Some(date(2024, 5, 9)), date(2024, 5, 9)), date(2024, 5, 13));
+        assert_eq!(Recurrence::Daily.next_due(None, date(2024, 12, 31)), date(2025, 1, 1));
+    }
+
+    #[test]
+    fn overdue_tasks_are_not_due_again_in_the_past() {
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon]);
+        assert_eq!(weekly.next_due(Some(date(2024, 5, 6)), date(2024, 5, 22)), date(2024, 5, 27));
+    }
+
+    #[test]
+    fn monthly_sticks_to_the_day_or_the_end_of_the_month() {
+        let monthly = Recurrence::Monthly(31);
+        assert_eq!(monthly.next_due(Some(date(2024, 1, 31)), date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(monthly.next_due(Some(date(2024, 2, 29)), date(2024, 2, 29)), date(2024, 3, 31));
+        assert_eq!(Recurrence::Monthly(15).next_due(None, date(2024, 3, 10)), date(2024, 3, 15));
+    }
+
+    #[test]
+    fn after_completion_counts_from_when_it_was_done() {
+        let rule = Recurrence::AfterCompletion(3);
+        assert_eq!(rule.next_due(Some(date(2024, 5, 1)), date(2024, 5, 10)), date(2024, 5, 13));
+    }
+}
@@ -0,0 +1,30 @@
+/// Anything that can be checked off. Frontends implement this for their own item types.
+pub trait Completable {
+    fn is_completed(&self) -> bool;
+    fn set_completed(&mut self, completed: bool);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub description: String,
+    pub completed: bool,
+}
+
+impl Task {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            completed: false,
+        }
+    }
+}
+
+impl Completable for Task {
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
+}
@@ -0,0 +1,229 @@
+//! Reader and writer for the [todo.txt](https://github.com/todotxt/todo.txt) format.
+//!
+//! Only the fixed-position header (completion marker, priority and dates) is parsed out.
+//! The description is kept verbatim, so `+project`, `@context`, `key:value` and any other
+//! tokens survive a round trip unchanged.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoTxtTask {
+    pub completed: bool,
+    /// `A` to `Z`, with `A` being the most important.
+    pub priority: Option<char>,
+    pub completion_date: Option<NaiveDate>,
+    pub creation_date: Option<NaiveDate>,
+    pub description: String,
+}
+
+impl TodoTxtTask {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            completed: false,
+            priority: None,
+            completion_date: None,
+            creation_date: None,
+            description: description.into(),
+        }
+    }
+
+    /// Parses a single line. Blank lines yield `None`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        let mut rest = line;
+        let completed = match rest.strip_prefix("x ") {
+            Some(after) => {
+                rest = after;
+                true
+            }
+            None => false,
+        };
+
+        // Priority only leads incomplete tasks; on a completed one it's part of the text
+        let priority = if completed { None } else { take_priority(&mut rest) };
+
+        let (completion_date, creation_date) = if completed {
+            let completion_date = take_date(&mut rest);
+            let creation_date = completion_date.and_then(|_| take_date(&mut rest));
+            (completion_date, creation_date)
+        } else {
+            (None, take_date(&mut rest))
+        };
+
+        Some(Self {
+            completed,
+            priority,
+            completion_date,
+            creation_date,
+            description: rest.to_string(),
+        })
+    }
+
+    pub fn projects(&self) -> impl Iterator<Item = &str> {
+        self.words().filter_map(|word| word.strip_prefix('+').filter(|name| !name.is_empty()))
+    }
+
+    pub fn contexts(&self) -> impl Iterator<Item = &str> {
+        self.words().filter_map(|word| word.strip_prefix('@').filter(|name| !name.is_empty()))
+    }
+
+    /// `key:value` extensions such as `due:2024-01-31`. URLs are not mistaken for tags.
+    pub fn tags(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.words()
+            .filter_map(|word| word.split_once(':'))
+            .filter(|(key, value)| !key.is_empty() && !value.is_empty() && !value.starts_with("//"))
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags().find(|(k, _)| *k == key).map(|(_, value)| value)
+    }
+
+    fn words(&self) -> impl Iterator<Item = &str> {
+        self.description.split_whitespace()
+    }
+}
+
+impl fmt::Display for TodoTxtTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.completed {
+            f.write_str("x ")?;
+        } else if let Some(priority) = self.priority {
+            write!(f, "({}) ", priority)?;
+        }
+        if self.completed {
+            if let Some(date) = self.completion_date {
+                write!(f, "{} ", date.format(DATE_FORMAT))?;
+            }
+        }
+        if let Some(date) = self.creation_date {
+            // A completed task's creation date is only recognised after its completion date
+            if !self.completed || self.completion_date.is_some() {
+                write!(f, "{} ", date.format(DATE_FORMAT))?;
+            }
+        }
+        f.write_str(&self.description)?;
+
+        // Completed tasks can't lead with a priority, so keep it the way other clients do
+        if let (true, Some(priority)) = (self.completed, self.priority) {
+            if self.tag("pri").is_none() {
+                write!(f, " pri:{}", priority)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a whole todo.txt file, skipping blank lines.
+pub fn parse(text: &str) -> Vec<TodoTxtTask> {
+    text.lines().filter_map(TodoTxtTask::parse).collect()
+}
+
+/// Writes tasks one per line, with a trailing newline.
+pub fn write<'a>(tasks: impl IntoIterator<Item = &'a TodoTxtTask>) -> String {
+    tasks.into_iter().map(|task| format!("{}\n", task)).collect()
+}
+
+fn take_priority(rest: &mut &str) -> Option<char> {
+    let bytes = rest.as_bytes();
+    match bytes {
+        [b'(', p @ b'A'..=b'Z', b')', b' ', ..] => {
+            *rest = &rest[4..];
+            Some(*p as char)
+        }
+        _ => None,
+    }
+}
+
+fn take_date(rest: &mut &str) -> Option<NaiveDate> {
+    let (word, after) = rest.split_once(' ').unwrap_or((rest, ""));
+    if word.len() != 10 {
+        return None;
+    }
+    let date = NaiveDate::parse_from_str(word, DATE_FORMAT).ok()?;
+    *rest = after;
+    Some(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let task = TodoTxtTask::parse("(A) 2011-03-01 Call Mom +Family @phone due:2011-03-05").unwrap();
+        assert!(!task.completed);
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.creation_date, date(2011, 3, 1));
+        assert_eq!(task.description, "Call Mom +Family @phone due:2011-03-05");
+        assert_eq!(task.projects().collect::<Vec<_>>(), ["Family"]);
+        assert_eq!(task.contexts().collect::<Vec<_>>(), ["phone"]);
+        assert_eq!(task.tag("due"), Some("2011-03-05"));
+
+        let done = TodoTxtTask::parse("x 2011-03-02 2011-03-01 Review pull request").unwrap();
+        assert!(done.completed);
+        assert_eq!(done.completion_date, date(2011, 3, 2));
+        assert_eq!(done.creation_date, date(2011, 3, 1));
+        assert_eq!(done.description, "Review pull request");
+    }
+
+    #[test]
+    fn malformed_headers_stay_in_the_description() {
+        let task = TodoTxtTask::parse("(a) 2011-13-01 xylophone lesson").unwrap();
+        assert_eq!(task.priority, None);
+        assert_eq!(task.creation_date, None);
+        assert_eq!(task.description, "(a) 2011-13-01 xylophone lesson");
+
+        let task = TodoTxtTask::parse("see https://example.com/a:b").unwrap();
+        assert_eq!(task.tags().count(), 0);
+    }
+
+    #[test]
+    fn round_trips_unknown_tokens() {
+        let text = "\
+(B) 2024-01-02 Plan trip +travel @home rec:+1w  t:2024-01-10 ★
+x 2024-01-05 2024-01-01 Pay rent pri:A
+x Done without dates
+Plain task
+";
+        let tasks = parse(text);
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(write(&tasks), text);
+    }
+
+    #[test]
+    fn completed_tasks_keep_their_creation_date() {
+        let task = TodoTxtTask {
+            completed: true,
+            priority: None,
+            completion_date: date(2024, 3, 2),
+            creation_date: date(2024, 3, 1),
+            description: "Renew passport".to_string(),
+        };
+        assert_eq!(task.to_string(), "x 2024-03-02 2024-03-01 Renew passport");
+        assert_eq!(TodoTxtTask::parse(&task.to_string()), Some(task.clone()));
+
+        // Without a completion date the creation date would be read as one, so it's left out
+        let undated = TodoTxtTask { completion_date: None, ..task };
+        assert_eq!(undated.to_string(), "x Renew passport");
+    }
+
+    #[test]
+    fn completed_priority_is_kept_as_a_tag() {
+        let mut task = TodoTxtTask::parse("(C) Water plants").unwrap();
+        task.completed = true;
+        task.completion_date = date(2024, 2, 1);
+        assert_eq!(task.to_string(), "x 2024-02-01 Water plants pri:C");
+    }
+}
@@ -0,0 +1,140 @@
+//! Exchanging todos with calendar clients as iCalendar `VTODO`s.
+//!
+//! A todo remembers the `VTODO` it was imported from, so properties this app has no use for
+//! go back out with it on export.
+
+use chrono::Utc;
+use dioxus::prelude::*;
+use todo_core::ical::{self, VTodo};
+
+use crate::todos::TodoList;
+use crate::TodoItem;
+
+const FILE_NAME: &str = "todos.ics";
+
+#[cfg(not(feature = "desktop"))]
+const DOWNLOAD_SCRIPT: &str = r#"
+    const text = await dioxus.recv();
+    const link = document.createElement("a");
+    link.href = URL.createObjectURL(new Blob([text], { type: "text/calendar" }));
+    link.download = "todos.ics";
+    link.click();
+    setTimeout(() => URL.revokeObjectURL(link.href));
+"#;
+
+impl TodoItem {
+    pub fn to_vtodo(&self) -> VTodo {
+        let mut todo = self.imported.clone().unwrap_or_default();
+        todo.uid = self.uid.clone();
+        todo.summary = self.contents.clone();
+        todo.completed = self.checked;
+        todo.completed_at = self.completed_at;
+        todo.due = self.due;
+        todo.priority = self.priority;
+        todo.categories = self.categories.clone();
+        todo
+    }
+
+    /// Takes over everything from `todo` except the id and position in the list.
+    pub fn update_from(&mut self, todo: VTodo) {
+        self.uid = todo.uid.clone();
+        self.contents = todo.summary.clone();
+        self.checked = todo.completed;
+        self.completed_at = todo.completed_at;
+        self.due = todo.due;
+        self.priority = todo.priority;
+        self.categories = todo.categories.clone();
+        self.imported = Some(todo);
+    }
+}
+
+#[derive(Props)]
+pub struct CalendarControlsProps<'a> {
+    todos: &'a UseState<TodoList>,
+}
+
+pub fn CalendarControls<'a>(cx: Scope<'a, CalendarControlsProps<'a>>) -> Element {
+    let status = use_state(cx, || None::<String>);
+    #[cfg(not(feature = "desktop"))]
+    let create_eval = use_eval(cx);
+
+    render! {
+        footer { class: "info calendar",
+            label { class: "import",
+                "Import .ics"
+                input {
+                    r#type: "file",
+                    accept: ".ics,text/calendar",
+                    onchange: move |evt| {
+                        let Some(files) = evt.files.clone() else { return };
+                        let todos = cx.props.todos.clone();
+                        let status = status.clone();
+                        cx.spawn(async move {
+                            let mut count = 0;
+                            for name in files.files() {
+                                if let Some(text) = files.read_file_to_string(&name).await {
+                                    let imported = ical::parse(&text);
+                                    count += imported.len();
+                                    todos.make_mut().import(imported);
+                                }
+                            }
+                            status.set(Some(format!("Imported {} {}", count, todo_core::task_noun(count))));
+                        });
+                    }
+                }
+            }
+            button {
+                class: "export",
+                disabled: if cx.props.todos.is_empty() { "true" } else { "false" },
+                onclick: move |_| {
+                    let text = ical::write(&cx.props.todos.export(), Utc::now().naive_utc());
+
+                    #[cfg(feature = "desktop")]
+                    status.set(Some(save_to_downloads(&text)));
+
+                    #[cfg(not(feature = "desktop"))]
+                    {
+                        let sent = create_eval(DOWNLOAD_SCRIPT).and_then(|eval| eval.send(text.into()));
+                        status.set(Some(match sent {
+                            Ok(()) => format!("Exported {}", FILE_NAME),
+                            Err(_) => "This renderer can't download files".to_string(),
+                        }));
+                    }
+                },
+                "Export .ics"
+            }
+            for message in status.get().iter() {
+                p { "{message}" }
+            }
+        }
+    }
+}
+
+/// Desktop has no downloads, so the file goes straight into the downloads folder. Earlier
+/// exports are kept, the new one becomes `todos (1).ics` and so on.
+#[cfg(feature = "desktop")]
+fn save_to_downloads(text: &str) -> String {
+    use std::fs::OpenOptions;
+    use std::io::{ErrorKind, Write};
+
+    let dir = dirs::download_dir().unwrap_or_default();
+    let (stem, extension) = FILE_NAME.rsplit_once('.').unwrap_or((FILE_NAME, ""));
+    for n in 0.. {
+        let path = match n {
+            0 => dir.join(FILE_NAME),
+            n => dir.join(format!("{} ({}).{}", stem, n, extension)),
+        };
+        // Only ever creates a file, so one that shows up meanwhile isn't overwritten either
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        return match written {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(e) => format!("Couldn't export to {}: {}", path.display(), e),
+        };
+    }
+    unreachable!("ran out of file names")
+}
@@ -1,45 +1,143 @@
 #![allow(non_snake_case)]
 
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
 use dioxus::prelude::*;
-use dioxus_elements::input_data::keyboard_types::Key;
+use dioxus_elements::input_data::keyboard_types::{Key, Modifiers};
+use todo_core::{Completable, Filter};
+
+mod calendar;
+mod route;
+mod todos;
+
+use calendar::CalendarControls;
+use todos::TodoList;
 
 fn main() {
-    dioxus_desktop::launch(app);
-}
+    #[cfg(feature = "desktop")]
+    {
+        // Desktop has no address bar, so the starting route can be passed as an argument
+        let initial_filter = std::env::args().nth(1).map(|arg| route::parse(&arg));
+        dioxus_desktop::launch_with_props(app, AppProps { initial_filter }, dioxus_desktop::Config::default());
+    }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-pub enum FilterState {
-    All,
-    Active,
-    Completed,
+    #[cfg(all(feature = "web", not(feature = "desktop")))]
+    dioxus_web::launch_with_props(app, AppProps::default(), dioxus_web::Config::default());
 }
 
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TodoItem {
     pub id: u32,
     pub checked: bool,
     pub contents: String,
+    pub due: Option<NaiveDate>,
+    /// Position in the list. Filters show todos in this order too.
+    pub order: u32,
+    /// Identifies the todo in exported calendars, and stays the same across exports
+    pub uid: String,
+    /// In UTC
+    pub completed_at: Option<NaiveDateTime>,
+    /// `1` is the most important and `9` the least, as in iCalendar
+    pub priority: Option<u8>,
+    pub categories: Vec<String>,
+    /// The calendar todo this one was imported from, see [`calendar`]
+    pub imported: Option<todo_core::ical::VTodo>,
 }
 
-pub fn app(cx: Scope<()>) -> Element {
-    let todos = use_state(cx, im_rc::HashMap::<u32, TodoItem>::default);
-    let list_filter = use_state(cx, || FilterState::All);
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DueStatus {
+    Overdue,
+    Today,
+    Upcoming,
+}
 
-    // Filter the todos based on the filter state
-    let mut filtered_todos = todos.iter()
-        .filter(|(_, item)| match **list_filter {
-            FilterState::All => true,
-            FilterState::Active => !item.checked,
-            FilterState::Completed => item.checked,
+impl TodoItem {
+    /// Completed todos are never overdue, whatever their date says.
+    pub fn due_status(&self, today: NaiveDate) -> Option<DueStatus> {
+        let due = self.due.filter(|_| !self.checked)?;
+        Some(match due.cmp(&today) {
+            std::cmp::Ordering::Less => DueStatus::Overdue,
+            std::cmp::Ordering::Equal => DueStatus::Today,
+            std::cmp::Ordering::Greater => DueStatus::Upcoming,
         })
-        .map(|f| *f.0)
+    }
+}
+
+impl Completable for TodoItem {
+    fn is_completed(&self) -> bool {
+        self.checked
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        if completed != self.checked {
+            self.checked = completed;
+            self.completed_at = completed.then(|| Utc::now().naive_utc());
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum FilterState {
+    #[default]
+    All,
+    Active,
+    Completed,
+    Overdue,
+    Today,
+}
+
+impl FilterState {
+    pub const ALL: [FilterState; 5] = [
+        FilterState::All,
+        FilterState::Active,
+        FilterState::Completed,
+        FilterState::Overdue,
+        FilterState::Today,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterState::All => Filter::All.label(),
+            FilterState::Active => Filter::Active.label(),
+            FilterState::Completed => Filter::Completed.label(),
+            FilterState::Overdue => "Overdue",
+            FilterState::Today => "Today",
+        }
+    }
+
+    pub fn matches(self, item: &TodoItem, today: NaiveDate) -> bool {
+        match self {
+            FilterState::All => Filter::All.matches(item),
+            FilterState::Active => Filter::Active.matches(item),
+            FilterState::Completed => Filter::Completed.matches(item),
+            FilterState::Overdue => item.due_status(today) == Some(DueStatus::Overdue),
+            FilterState::Today => item.due_status(today) == Some(DueStatus::Today),
+        }
+    }
+}
+
+#[derive(Props, PartialEq, Default)]
+pub struct AppProps {
+    initial_filter: Option<FilterState>,
+}
+
+pub fn app(cx: Scope<AppProps>) -> Element {
+    let todos = use_state(cx, TodoList::default);
+    let list_filter = route::use_route(cx, cx.props.initial_filter);
+    let dragging = use_state(cx, || None::<u32>);
+    let today = Local::now().date_naive();
+
+    // Filter the todos based on the filter state
+    let filtered_todos = todos.ordered_ids()
+        .into_iter()
+        .filter(|id| list_filter.matches(&todos[id], today))
         .collect::<Vec<_>>();
-    filtered_todos.sort_unstable();
 
-    let active_todo_count = todos.values().filter(|item| !item.checked).count();
-    let active_todo_text = if active_todo_count == 1 { "task" } else { "tasks" };
+    let active_todo_count = todo_core::count_active(todos.values());
+    let active_todo_text = todo_core::task_noun(active_todo_count);
 
-    let show_clear_completed = todos.values().any(|todo| todo.checked);
+    let show_clear_completed = todo_core::any_completed(todos.values());
 
     render! {
         section { class: "todoapp",
@@ -59,37 +157,38 @@ pub fn app(cx: Scope<()>) -> Element {
                         class: "toggle-all",
                         r#type: "checkbox",
                         onchange: move |_| {
-                            let check = active_todo_count != 0;
-                            for (_, item) in todos.make_mut().iter_mut() {
-                                item.checked = check;
-                            }
+                            todo_core::toggle_all(todos.make_mut().values_mut());
                         },
                         checked: if active_todo_count == 0 { "true" } else { "false" },
                     }
                     label { r#for: "toggle-all" }
                 }}
                 ul { class: "todo-list",
-                    for id in filtered_todos.iter() {
+                    for (i, id) in filtered_todos.iter().enumerate() {
                         TodoEntry {
                             key: "{id}",
                             id: *id,
                             todos: todos,
+                            dragging: dragging,
+                            // Keyboard moves skip over todos hidden by the filter
+                            prev: i.checked_sub(1).map(|prev| filtered_todos[prev]),
+                            next: filtered_todos.get(i + 1).copied(),
                         }
                     }
                 }
             }
         }
+        CalendarControls { todos: todos }
     }
 }
 
 #[derive(Props)]
 pub struct TodoHeaderProps<'a> {
-    todos: &'a UseState<im_rc::HashMap<u32, TodoItem>>,
+    todos: &'a UseState<TodoList>,
 }
 
 pub fn TodoHeader<'a>(cx: Scope<'a, TodoHeaderProps<'a>>) -> Element {
     let draft = use_state(cx, || "".to_string());
-    let todo_id = use_state(cx, || 0);
 
     render! {
         header { class: "header",
@@ -102,17 +201,7 @@ pub fn TodoHeader<'a>(cx: Scope<'a, TodoHeaderProps<'a>>) -> Element {
                 oninput: move |evt| draft.set(evt.value.clone()),
                 onkeydown: move |evt| {
                     if evt.key() == Key::Enter && !draft.is_empty() {
-                        cx.props.todos
-                            .make_mut()
-                            .insert(
-                                **todo_id,
-                                TodoItem {
-                                    id: **todo_id,
-                                    checked: false,
-                                    contents: draft.to_string(),
-                                },
-                            );
-                        *todo_id.make_mut() += 1;
+                        cx.props.todos.make_mut().add(draft.to_string());
                         draft.set("".to_string());
                     }
                 }
@@ -123,20 +212,68 @@ pub fn TodoHeader<'a>(cx: Scope<'a, TodoHeaderProps<'a>>) -> Element {
 
 #[derive(Props)]
 pub struct TodoEntryProps<'a> {
-    todos: &'a UseState<im_rc::HashMap<u32, TodoItem>>,
+    todos: &'a UseState<TodoList>,
     id: u32,
+    /// The todo currently being dragged, shared by all entries
+    dragging: &'a UseState<Option<u32>>,
+    prev: Option<u32>,
+    next: Option<u32>,
 }
 
 pub fn TodoEntry<'a>(cx: Scope<'a, TodoEntryProps<'a>>) -> Element {
     let is_editing = use_state(cx, || false);
+    let is_drop_target = use_state(cx, || false);
 
     let todos = cx.props.todos.get();
     let todo = &todos[&cx.props.id];
     let completed = if todo.checked { "completed" } else { "" };
     let editing = if **is_editing { "editing" } else { "" };
+    let due = match todo.due_status(Local::now().date_naive()) {
+        Some(DueStatus::Overdue) => "has-due overdue",
+        Some(DueStatus::Today) => "has-due due-today",
+        Some(DueStatus::Upcoming) => "has-due",
+        None if todo.due.is_some() => "has-due",
+        None => "",
+    };
+    let due_value = todo.due.map(|due| due.format(DATE_FORMAT).to_string()).unwrap_or_default();
+    let drag = match **cx.props.dragging {
+        Some(id) if id == cx.props.id => "dragging",
+        Some(_) if **is_drop_target => "drop-target",
+        _ => "",
+    };
+
+    let move_to = move |target: Option<u32>| {
+        if let Some(target) = target {
+            cx.props.todos.make_mut().move_todo(cx.props.id, target);
+        }
+    };
 
     render! {
-        li { class: "{completed} {editing}",
+        li { class: "{completed} {editing} {due} {drag}",
+            draggable: if **is_editing { "false" } else { "true" },
+            prevent_default: "ondragover ondrop",
+            ondragstart: move |_| cx.props.dragging.set(Some(cx.props.id)),
+            ondragend: move |_| cx.props.dragging.set(None),
+            ondragenter: move |_| is_drop_target.set(true),
+            ondragleave: move |_| is_drop_target.set(false),
+            ondragover: move |_| {},
+            ondrop: move |_| {
+                is_drop_target.set(false);
+                if let Some(id) = **cx.props.dragging {
+                    cx.props.todos.make_mut().move_todo(id, cx.props.id);
+                }
+                cx.props.dragging.set(None);
+            },
+            onkeydown: move |evt| {
+                if **is_editing || !evt.modifiers().contains(Modifiers::ALT) {
+                    return;
+                }
+                match evt.key() {
+                    Key::ArrowUp => move_to(cx.props.prev),
+                    Key::ArrowDown => move_to(cx.props.next),
+                    _ => {}
+                }
+            },
             div { class: "view",
                 input {
                     class: "toggle",
@@ -145,7 +282,7 @@ pub fn TodoEntry<'a>(cx: Scope<'a, TodoEntryProps<'a>>) -> Element {
                     checked: "{todo.checked}",
                     oninput: move |evt| {
                         let mut todos = cx.props.todos.make_mut();
-                        todos[&cx.props.id].checked = evt.value.parse().unwrap();
+                        todos[&cx.props.id].set_completed(evt.value.parse().unwrap());
                     }
                 }
                 label {
@@ -154,6 +291,17 @@ pub fn TodoEntry<'a>(cx: Scope<'a, TodoEntryProps<'a>>) -> Element {
                     prevent_default: "onclick",
                     "{todo.contents}"
                 }
+                input {
+                    class: "due",
+                    r#type: "date",
+                    title: "Due date",
+                    value: "{due_value}",
+                    oninput: move |evt| {
+                        let mut todos = cx.props.todos.make_mut();
+                        // An empty value means the date was cleared
+                        todos[&cx.props.id].due = NaiveDate::parse_from_str(&evt.value, DATE_FORMAT).ok();
+                    }
+                }
                 button {
                     class: "change",
                     onclick: move |_| is_editing.set(true),
@@ -190,7 +338,7 @@ pub fn TodoEntry<'a>(cx: Scope<'a, TodoEntryProps<'a>>) -> Element {
 
 #[derive(Props)]
 pub struct ListToolbarProps<'a> {
-    todos: &'a UseState<im_rc::HashMap<u32, TodoItem>>,
+    todos: &'a UseState<TodoList>,
     active_todo_count: usize,
     active_todo_text: &'a str,
     show_clear_completed: bool,
@@ -200,6 +348,7 @@ pub struct ListToolbarProps<'a> {
 pub fn ListToolbar<'a>(cx: Scope<'a, ListToolbarProps<'a>>) -> Element {
     let active_todo_count = cx.props.active_todo_count;
     let active_todo_text = cx.props.active_todo_text;
+    let create_eval = use_eval(cx);
 
     let selected = |state| {
         if *cx.props.list_filter == state { "selected" } else { "false" }
@@ -212,18 +361,18 @@ pub fn ListToolbar<'a>(cx: Scope<'a, ListToolbarProps<'a>>) -> Element {
                 span { "{active_todo_text}" }
             }
             ul { class: "filters",
-                for (state , state_text , url) in [
-                    (FilterState::All, "All", "#/"),
-                    (FilterState::Active, "Active", "#/active"),
-                    (FilterState::Completed, "Completed", "#/completed"),
-                ] { rsx! {
+                for state in FilterState::ALL { rsx! {
                     li {
                         a {
-                            href: url,
+                            href: route::href(state),
                             class: selected(state),
-                            onclick: move |_| cx.props.list_filter.set(state),
+                            onclick: move |_| {
+                                if create_eval(&route::navigate_script(state)).is_err() {
+                                    cx.props.list_filter.set(state);
+                                }
+                            },
                             prevent_default: "onclick",
-                            {state_text}
+                            {state.label()}
                         }
                     }
                 }}
@@ -233,7 +382,7 @@ pub fn ListToolbar<'a>(cx: Scope<'a, ListToolbarProps<'a>>) -> Element {
                     class: "clear-completed",
                     onclick: move |_| {
                         let mut todos = cx.props.todos.make_mut();
-                        todos.retain(|_, todo| !todo.checked)
+                        todos.retain(|_, todo| Filter::Active.matches(todo))
                     },
                     "Clear Complete"
                 }
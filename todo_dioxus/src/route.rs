@@ -0,0 +1,73 @@
+//! TodoMVC routing: the list filter lives in the URL fragment (`#/`, `#/active`, `#/completed`,
+//! plus `#/overdue` and `#/today`).
+//!
+//! The fragment is the source of truth. Clicking a filter only changes `location.hash`,
+//! and a `hashchange` listener feeds the new route back, so browser history and
+//! back/forward navigation move between filters as well.
+
+use dioxus::prelude::*;
+
+use crate::FilterState;
+
+pub fn href(filter: FilterState) -> &'static str {
+    match filter {
+        FilterState::All => "#/",
+        FilterState::Active => "#/active",
+        FilterState::Completed => "#/completed",
+        FilterState::Overdue => "#/overdue",
+        FilterState::Today => "#/today",
+    }
+}
+
+/// Parses a route with or without the leading `#`. Unknown routes show everything.
+pub fn parse(route: &str) -> FilterState {
+    match route.trim_start_matches('#').trim_end_matches('/') {
+        "/active" => FilterState::Active,
+        "/completed" => FilterState::Completed,
+        "/overdue" => FilterState::Overdue,
+        "/today" => FilterState::Today,
+        _ => FilterState::All,
+    }
+}
+
+/// The current filter, kept in sync with the URL fragment.
+///
+/// `initial` is only used when the page was opened without a fragment.
+pub fn use_route(cx: &ScopeState, initial: Option<FilterState>) -> &UseState<FilterState> {
+    let filter = use_state(cx, || initial.unwrap_or_default());
+    let create_eval = use_eval(cx);
+
+    use_future(cx, (), |_| {
+        let filter = filter.clone();
+        let create_eval = create_eval.clone();
+        let script = format!(
+            r#"
+            const initial = "{}";
+            if (initial && !window.location.hash) {{
+                history.replaceState(null, "", initial);
+            }}
+            const send = () => dioxus.send(window.location.hash);
+            window.addEventListener("hashchange", send);
+            send();
+            "#,
+            initial.map(href).unwrap_or_default(),
+        );
+
+        async move {
+            // Renderers without a URL just keep the in-memory filter
+            let Ok(listener) = create_eval(&script) else { return };
+            while let Ok(hash) = listener.recv().await {
+                if let Some(hash) = hash.as_str() {
+                    filter.set(parse(hash));
+                }
+            }
+        }
+    });
+
+    filter
+}
+
+/// Script that navigates to `filter`, adding a history entry.
+pub fn navigate_script(filter: FilterState) -> String {
+    format!(r#"window.location.hash = "{}";"#, href(filter))
+}
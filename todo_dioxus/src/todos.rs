@@ -0,0 +1,212 @@
+//! The todo list and the ids in it.
+//!
+//! Ids are handed out here and nowhere else, and only go up, even past deleted todos. Whatever
+//! gets added later goes through [`TodoList::add`] and can't reuse an id and overwrite a todo.
+//!
+//! The ids only last as long as the list in memory, the app doesn't save it. Across sessions
+//! and in calendar files a todo is known by its UID, which is how [`TodoList::import`] finds
+//! the todos it already has.
+
+use std::ops::{Index, IndexMut};
+
+use todo_core::ical::VTodo;
+use uuid::Uuid;
+
+use crate::TodoItem;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TodoList {
+    items: im_rc::HashMap<u32, TodoItem>,
+    next_id: u32,
+}
+
+impl TodoList {
+    fn allocate_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn next_order(&self) -> u32 {
+        self.items.values().map(|item| item.order + 1).max().unwrap_or(0)
+    }
+
+    /// Adds a new todo at the end of the list and returns its id.
+    pub fn add(&mut self, contents: String) -> u32 {
+        let id = self.allocate_id();
+        let order = self.next_order();
+        self.items.insert(id, TodoItem {
+            id,
+            checked: false,
+            contents,
+            due: None,
+            order,
+            uid: Uuid::new_v4().to_string(),
+            completed_at: None,
+            priority: None,
+            categories: Vec::new(),
+            imported: None,
+        });
+        id
+    }
+
+    /// Adds todos from a calendar at the end of the list. A todo that was imported or
+    /// exported before is recognised by its UID and updated instead of added twice.
+    pub fn import(&mut self, todos: impl IntoIterator<Item = VTodo>) {
+        for todo in todos {
+            let existing = self.items.values().find(|item| !todo.uid.is_empty() && item.uid == todo.uid).map(|item| item.id);
+            let id = match existing {
+                Some(id) => id,
+                None => self.add(String::new()),
+            };
+            let item = &mut self.items[&id];
+            let uid = std::mem::take(&mut item.uid);
+            item.update_from(todo);
+            // Todos from clients that leave the UID out get the one just made up for them
+            if item.uid.is_empty() {
+                item.uid = uid;
+            }
+        }
+    }
+
+    /// Every todo as a calendar todo, in list order.
+    pub fn export(&self) -> Vec<VTodo> {
+        self.ordered_ids().iter().map(|id| self.items[id].to_vtodo()).collect()
+    }
+
+    pub fn remove(&mut self, id: &u32) -> Option<TodoItem> {
+        self.items.remove(id)
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&u32, &TodoItem) -> bool) {
+        self.items.retain(f);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &TodoItem> {
+        self.items.values()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut TodoItem> {
+        self.items.iter_mut().map(|(_, item)| item)
+    }
+
+    /// Ids of all todos in list order.
+    pub fn ordered_ids(&self) -> Vec<u32> {
+        let mut ids = self.items.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable_by_key(|id| (self.items[id].order, *id));
+        ids
+    }
+
+    /// Moves `id` to where `target` is. Moving down puts it after `target`, moving up before it.
+    pub fn move_todo(&mut self, id: u32, target: u32) {
+        let mut ids = self.ordered_ids();
+        let (Some(from), Some(to)) = (ids.iter().position(|x| *x == id), ids.iter().position(|x| *x == target)) else {
+            return;
+        };
+        ids.remove(from);
+        ids.insert(to, id);
+
+        for (order, id) in ids.into_iter().enumerate() {
+            self.items[&id].order = order as u32;
+        }
+    }
+}
+
+impl Index<&u32> for TodoList {
+    type Output = TodoItem;
+
+    fn index(&self, id: &u32) -> &TodoItem {
+        &self.items[id]
+    }
+}
+
+impl IndexMut<&u32> for TodoList {
+    fn index_mut(&mut self, id: &u32) -> &mut TodoItem {
+        &mut self.items[id]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_of(names: &[&str]) -> TodoList {
+        let mut list = TodoList::default();
+        for name in names {
+            list.add(name.to_string());
+        }
+        list
+    }
+
+    fn contents(list: &TodoList) -> Vec<&str> {
+        list.ordered_ids().iter().map(|id| list[id].contents.as_str()).collect()
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut list = list_of(&["a", "b", "c"]);
+        list.remove(&2);
+        list.retain(|id, _| *id != 1);
+        let id = list.add("d".to_string());
+        assert_eq!(id, 3);
+
+        // Not even after everything is gone
+        list.retain(|_, _| false);
+        assert_eq!(list.add("e".to_string()), 4);
+        assert_eq!(contents(&list), ["e"]);
+    }
+
+    #[test]
+    fn added_todos_go_last() {
+        let mut list = list_of(&["a", "b"]);
+        list.move_todo(1, 0);
+        list.add("c".to_string());
+        assert_eq!(contents(&list), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn move_todo_reaches_both_ends() {
+        let mut list = list_of(&["a", "b", "c", "d"]);
+        list.move_todo(0, 3);
+        assert_eq!(contents(&list), ["b", "c", "d", "a"]);
+        list.move_todo(0, 1);
+        assert_eq!(contents(&list), ["a", "b", "c", "d"]);
+        list.move_todo(3, 0);
+        assert_eq!(contents(&list), ["d", "a", "b", "c"]);
+        list.move_todo(3, 2);
+        assert_eq!(contents(&list), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn reimporting_updates_todos_instead_of_adding_them() {
+        let mut list = list_of(&["local"]);
+        let mut milk = VTodo::new("milk@example.com", "Buy milk");
+        list.import([milk.clone(), VTodo::new("", "No UID")]);
+        assert_eq!(contents(&list), ["local", "Buy milk", "No UID"]);
+        assert!(!list[&2].uid.is_empty());
+
+        milk.summary = "Buy oat milk".to_string();
+        milk.completed = true;
+        list.import([milk]);
+        assert_eq!(contents(&list), ["local", "Buy oat milk", "No UID"]);
+        assert!(list[&1].checked);
+
+        // An exported todo comes back as itself
+        let exported = list.export();
+        list.import(exported);
+        assert_eq!(contents(&list), ["local", "Buy oat milk", "No UID"]);
+    }
+
+    #[test]
+    fn move_todo_ignores_unknown_ids() {
+        let mut list = list_of(&["a", "b"]);
+        list.move_todo(0, 7);
+        list.move_todo(7, 0);
+        list.move_todo(1, 1);
+        assert_eq!(contents(&list), ["a", "b"]);
+    }
+}
@@ -17,8 +17,17 @@ impl<W: Widget<AppState>> Controller<AppState, W> for AddTodoController {
         match event {
             Event::KeyDown(k_e) if HotKey::new(None, KbKey::Enter).matches(k_e) => {
                 data.add_todo();
+                ctx.submit_command(SAVE);
                 ctx.set_handled();
             }
+            Event::KeyDown(k_e) if HotKey::new(None, KbKey::Tab).matches(k_e) => {
+                // Tab accepts the first tag suggestion instead of moving focus
+                if let Some(tag) = data.tag_completions().front().cloned() {
+                    data.complete_tag(&tag);
+                    ctx.set_handled();
+                    return;
+                }
+            }
             _ => {}
         }
         child.event(ctx, event, data, env);
@@ -1,13 +1,22 @@
+use std::collections::BTreeSet;
+
+use chrono::{Local, NaiveDate};
 use druid::im::Vector;
 use druid::text::{Attribute, RichText};
-use druid::{Data, Env, EventCtx, FontStyle, Lens, Selector};
+use druid::{Data, Env, EventCtx, FontStyle, FontWeight, Lens, Selector};
+use todo_core::{Completable, Filter, Recurrence};
 use uuid::Uuid;
 
+use crate::{schedule, tags};
+
 pub const REBUILD: Selector<Uuid> = Selector::new("todo.rebuild");
 pub const SELECT: Selector<Uuid> = Selector::new("todo.select");
 pub const UNSELECT: Selector<Uuid> = Selector::new("todo.unselect");
 pub const EDIT: Selector<Uuid> = Selector::new("todo.edit");
 pub const DELETE: Selector<Uuid> = Selector::new("todo.delete");
+pub const SAVE: Selector = Selector::new("todo.save");
+pub const FILTER_TAG: Selector<String> = Selector::new("todo.filter-tag");
+pub const COMPLETE_TAG: Selector<String> = Selector::new("todo.complete-tag");
 
 #[derive(Clone, Data, Lens)]
 pub struct TodoItem {
@@ -20,6 +29,29 @@ pub struct TodoItem {
     // We use this to remember what the text was before an edit, in case it's cancelled
     stash: String,
     rendered: RichText,
+    /// Lowercased `#tag` words from the text
+    pub tags: Vector<String>,
+    /// From the `due:` and `repeat:` words in the text
+    #[data(same_fn = "PartialEq::eq")]
+    pub due: Option<NaiveDate>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub recurrence: Option<Recurrence>,
+    // todo.txt details that aren't shown, kept so an export doesn't lose them
+    pub priority: Option<char>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub created: Option<NaiveDate>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub completed_on: Option<NaiveDate>,
+}
+
+impl Completable for TodoItem {
+    fn is_completed(&self) -> bool {
+        self.done
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.done = completed;
+    }
 }
 
 impl TodoItem {
@@ -32,9 +64,29 @@ impl TodoItem {
             text: text.to_string(),
             stash: text.to_string(),
             rendered: TodoItem::render(text, false),
+            tags: tags::extract_tags(text),
+            due: schedule::due_date(text),
+            recurrence: schedule::recurrence(text),
+            priority: None,
+            created: Some(Local::now().date_naive()),
+            completed_on: None,
+        }
+    }
+
+    /// Recreates a todo that was previously saved.
+    pub fn restore(id: Uuid, text: &str, done: bool) -> Self {
+        Self {
+            id,
+            done,
+            rendered: TodoItem::render(text, done),
+            ..Self::new(text)
         }
     }
 
+    pub fn description(&self) -> &str {
+        &self.text
+    }
+
     pub fn double_click(ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
         data.editing = true;
         ctx.request_layout();
@@ -58,12 +110,40 @@ impl TodoItem {
                 .with_attribute(0.., Attribute::style(FontStyle::Italic))
                 .with_attribute(0.., Attribute::text_color(druid::theme::PLACEHOLDER_COLOR))
         } else {
-            RichText::new(text.into())
+            let rich = tags::tag_spans(text).into_iter().fold(RichText::new(text.into()), |rich, (range, name)| {
+                rich.with_attribute(range.clone(), Attribute::text_color(tags::tag_color(name)))
+                    .with_attribute(range, Attribute::weight(FontWeight::BOLD))
+            });
+            schedule::schedule_spans(text).into_iter().fold(rich, |rich, range| {
+                rich.with_attribute(range.clone(), Attribute::style(FontStyle::Italic))
+                    .with_attribute(range, Attribute::text_color(druid::theme::PLACEHOLDER_COLOR))
+            })
         }
     }
 
     pub fn rebuild(&mut self) {
         self.rendered = Self::render(&self.text, self.done);
+        self.tags = tags::extract_tags(&self.text);
+        self.due = schedule::due_date(&self.text);
+        self.recurrence = schedule::recurrence(&self.text);
+    }
+
+    /// Dates a completion `today`, and forgets the date again once the todo is reopened.
+    pub fn stamp_completion(&mut self, today: NaiveDate) {
+        if !self.done {
+            self.completed_on = None;
+        } else if self.completed_on.is_none() {
+            self.completed_on = Some(today);
+        }
+    }
+
+    /// A fresh copy of a completed recurring todo, due on the next date of its schedule.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<TodoItem> {
+        let recurrence = self.recurrence.as_ref().filter(|_| self.done)?;
+        let due = recurrence.next_due(self.due, today);
+        let mut next = TodoItem::new(&schedule::with_due(&self.text, due));
+        next.priority = self.priority;
+        Some(next)
     }
 
     pub fn gain_selection(&mut self) {
@@ -79,23 +159,31 @@ impl TodoItem {
     pub fn lose_selection(&mut self) {
         self.editing = false;
         self.selected = false;
-        self.rendered = Self::render(&self.text, self.done);
+        self.rebuild();
     }
 }
 
+#[derive(Clone, Data)]
+pub struct TagChip {
+    pub name: String,
+    pub selected: bool,
+}
+
 #[derive(Clone, Data, Lens)]
 pub struct AppState {
     pub todos: Vector<TodoItem>,
-    pub filtered_ids: Vector<usize>,
+    /// Ids of the todos the filters let through, in list order. Ids rather than positions,
+    /// so a view that's a step behind can never show the wrong todo.
+    #[data(same_fn = "PartialEq::eq")]
+    pub filtered_ids: Vector<Uuid>,
     new_todo: String,
+    #[data(same_fn = "PartialEq::eq")]
     filter: Filter,
+    tag_filter: Option<String>,
     #[data(same_fn = "PartialEq::eq")]
     pub selected: Option<Uuid>,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Data)]
-enum Filter { All, Active, Completed }
-
 impl AppState {
     pub fn new() -> Self {
         Self {
@@ -103,12 +191,30 @@ impl AppState {
             filtered_ids: Vector::new(),
             new_todo: String::new(),
             filter: Filter::All,
+            tag_filter: None,
             selected: None,
         }
     }
 
+    pub fn with_todos(todos: Vector<TodoItem>) -> Self {
+        let mut state = Self::new();
+        state.set_todos(todos);
+        state
+    }
+
+    /// Replaces the whole list, e.g. after opening a file.
+    pub fn set_todos(&mut self, todos: Vector<TodoItem>) {
+        self.todos = todos;
+        self.selected = None;
+        self.update_filtered();
+    }
+
     pub fn has_completed_todos(&self) -> bool {
-        self.todos.iter().any(|todo| todo.done)
+        todo_core::any_completed(&self.todos)
+    }
+
+    pub fn active_count(&self) -> usize {
+        todo_core::count_active(&self.todos)
     }
 
     pub fn add_todo(&mut self) {
@@ -127,57 +233,381 @@ impl AppState {
         if let Some(idx) = idx {
             self.todos.remove(idx);
         }
+        if self.selected.as_ref() == Some(id) {
+            self.selected = None;
+        }
+        self.update_filtered();
+    }
+
+    /// Completing a recurring todo adds its next occurrence right above it. The schedule moves
+    /// to the new todo, so unchecking and checking the old one doesn't repeat it twice.
+    pub fn schedule_next(&mut self, id: &Uuid, today: NaiveDate) {
+        let index = match self.todos.iter().position(|todo| todo.id == *id) {
+            Some(index) => index,
+            None => return,
+        };
+        let next = match self.todos[index].next_occurrence(today) {
+            Some(next) => next,
+            None => return,
+        };
+
+        let done = &mut self.todos[index];
+        done.text = schedule::without_recurrence(&done.text);
+        done.completed_on = Some(today);
+        done.rebuild();
+
+        self.todos.insert(index, next);
+        self.update_filtered();
+    }
+
+    pub fn toggle_all(ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
+        data.toggle_all_todos(Local::now().date_naive());
+        ctx.submit_command(SAVE);
+    }
+
+    pub fn toggle_all_todos(&mut self, today: NaiveDate) {
+        let open: Vec<Uuid> = self.todos.iter().filter(|todo| !todo.done).map(|todo| todo.id).collect();
+        todo_core::toggle_all(self.todos.iter_mut());
+        // Rows the filter hides don't rebuild themselves
+        for todo in self.todos.iter_mut() {
+            todo.rebuild();
+            todo.stamp_completion(today);
+        }
+        // Recurring todos completed this way repeat just like checked ones
+        for id in &open {
+            self.schedule_next(id, today);
+        }
+        self.update_filtered();
     }
 
-    pub fn toggle_all(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
-        let check = data.todos.iter().filter(|todo| !todo.done).count() > 0;
-        data.todos.iter_mut().for_each(|todo| todo.done = check);
+    pub fn clear_completed(ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
+        data.remove_completed();
+        ctx.submit_command(SAVE);
     }
 
-    pub fn clear_completed(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
-        let new_todos: Vector<TodoItem> = data.todos.iter()
+    pub fn remove_completed(&mut self) {
+        let new_todos: Vector<TodoItem> = self.todos.iter()
             .cloned()
-            .filter(|item| !item.done)
+            .filter(|item| Filter::Active.matches(item))
             .collect();
 
-        data.todos = new_todos;
-        data.update_filtered();
+        self.todos = new_todos;
+        if self.selected.map_or(false, |id| !self.todos.iter().any(|todo| todo.id == id)) {
+            self.selected = None;
+        }
+        self.update_filtered();
     }
 
     pub fn filter_all(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
-        data.filter = Filter::All;
-        data.update_filtered();
+        data.set_filter(Filter::All);
     }
 
     pub fn filter_active(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
-        data.filter = Filter::Active;
-        data.update_filtered();
+        data.set_filter(Filter::Active);
     }
 
     pub fn filter_completed(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
-        data.filter = Filter::Completed;
-        data.update_filtered();
+        data.set_filter(Filter::Completed);
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.update_filtered();
+    }
+
+    /// Shows only todos with `tag`, or stops filtering by tag if it was already selected.
+    pub fn toggle_tag_filter(&mut self, tag: &str) {
+        if self.tag_filter.as_deref() == Some(tag) {
+            self.tag_filter = None;
+        } else {
+            self.tag_filter = Some(tag.to_string());
+        }
+        self.update_filtered();
+    }
+
+    /// Every tag in use, plus the selected one even if no todo has it anymore.
+    pub fn tag_chips(&self) -> Vector<TagChip> {
+        let mut names: BTreeSet<&str> = self.todos.iter()
+            .flat_map(|todo| todo.tags.iter().map(String::as_str))
+            .collect();
+        names.extend(self.tag_filter.as_deref());
+
+        names.into_iter()
+            .map(|name| TagChip {
+                name: name.to_string(),
+                selected: self.tag_filter.as_deref() == Some(name),
+            })
+            .collect()
+    }
+
+    /// Known tags that complete the `#tag` being typed into the new todo.
+    pub fn tag_completions(&self) -> Vector<String> {
+        let prefix = match tags::tag_prefix(&self.new_todo) {
+            Some(prefix) => prefix.to_lowercase(),
+            None => return Vector::new(),
+        };
+        self.tag_chips().into_iter()
+            .map(|chip| chip.name)
+            .filter(|name| name.starts_with(&prefix) && *name != prefix)
+            .collect()
+    }
+
+    /// Replaces the partially typed tag at the end of the new todo with `tag`.
+    pub fn complete_tag(&mut self, tag: &str) {
+        if let Some(prefix) = tags::tag_prefix(&self.new_todo) {
+            let start = self.new_todo.len() - prefix.len();
+            self.new_todo.truncate(start);
+            self.new_todo.push_str(tag);
+            self.new_todo.push(' ');
+        }
     }
 
     pub fn update_filtered(&mut self) {
-        match self.filter {
-            Filter::All => {
-                self.filtered_ids = (0..self.todos.len()).collect();
-            },
-            Filter::Active => {
-                self.filtered_ids = self.todos.iter()
-                    .enumerate()
-                    .filter(|(_, todo)| !todo.done)
-                    .map(|(idx, _)| idx)
-                    .collect();
-            },
-            Filter::Completed => {
-                self.filtered_ids = self.todos.iter()
-                    .enumerate()
-                    .filter(|(_, todo)| todo.done)
-                    .map(|(idx, _)| idx)
-                    .collect();
-            },
+        let tag_filter = self.tag_filter.as_ref();
+        self.filtered_ids = self.todos.iter()
+            .filter(|todo| self.filter.matches(*todo))
+            .filter(|todo| tag_filter.map_or(true, |tag| todo.tags.contains(tag)))
+            .map(|todo| todo.id)
+            .collect();
+    }
+
+    /// The todos in `filtered_ids`, for the list view.
+    pub fn filtered_todos(&self) -> Vector<TodoItem> {
+        self.todos.iter()
+            .filter(|todo| self.filtered_ids.contains(&todo.id))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::delegate::Delegate;
+
+    fn state_with(texts: &[&str]) -> AppState {
+        AppState::with_todos(texts.iter().map(|text| TodoItem::new(text)).collect())
+    }
+
+    fn texts(state: &AppState) -> Vec<String> {
+        state.filtered_todos().iter().map(|todo| todo.description().to_string()).collect()
+    }
+
+    /// What `filtered_ids` should be, worked out from scratch.
+    fn expected_filtered(state: &AppState) -> Vector<Uuid> {
+        state.todos.iter()
+            .filter(|todo| state.filter.matches(*todo))
+            .filter(|todo| state.tag_filter.as_ref().map_or(true, |tag| todo.tags.contains(tag)))
+            .map(|todo| todo.id)
+            .collect()
+    }
+
+    #[test]
+    fn add_todo_puts_it_first_and_clears_the_input() {
+        let mut state = state_with(&["older"]);
+        state.new_todo = "newer".to_string();
+        state.add_todo();
+
+        assert_eq!(texts(&state), ["newer", "older"]);
+        assert!(state.new_todo.is_empty());
+        assert_eq!(state.active_count(), 2);
+    }
+
+    #[test]
+    fn filters_show_matching_todos() {
+        let mut state = state_with(&["a", "b", "c"]);
+        state.todos[1].done = true;
+
+        state.set_filter(Filter::Active);
+        assert_eq!(texts(&state), ["a", "c"]);
+        state.set_filter(Filter::Completed);
+        assert_eq!(texts(&state), ["b"]);
+        state.set_filter(Filter::All);
+        assert_eq!(texts(&state), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn tag_filter_combines_with_the_filter() {
+        let mut state = state_with(&["milk #shop", "bread #Shop", "call mum"]);
+        state.todos[1].done = true;
+
+        state.toggle_tag_filter("shop");
+        assert_eq!(texts(&state), ["milk #shop", "bread #Shop"]);
+        state.set_filter(Filter::Active);
+        assert_eq!(texts(&state), ["milk #shop"]);
+
+        state.toggle_tag_filter("shop");
+        assert_eq!(texts(&state), ["milk #shop", "call mum"]);
+    }
+
+    #[test]
+    fn toggle_all_completes_then_reopens() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let mut state = state_with(&["a", "b"]);
+        state.todos[0].done = true;
+        state.todos[0].completed_on = NaiveDate::from_ymd_opt(2024, 5, 1);
+
+        state.toggle_all_todos(today);
+        assert!(state.todos.iter().all(|todo| todo.done));
+        assert_eq!(state.todos[0].completed_on, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(state.todos[1].completed_on, Some(today));
+        state.toggle_all_todos(today);
+        assert!(state.todos.iter().all(|todo| !todo.done && todo.completed_on.is_none()));
+    }
+
+    #[test]
+    fn remove_completed_keeps_active_todos_in_order() {
+        let mut state = state_with(&["a", "b", "c", "d"]);
+        state.todos[0].done = true;
+        state.todos[2].done = true;
+
+        state.remove_completed();
+        assert_eq!(texts(&state), ["b", "d"]);
+        assert!(!state.has_completed_todos());
+    }
+
+    #[test]
+    fn tag_completions_follow_the_typed_prefix() {
+        let mut state = state_with(&["milk #shop", "#school run"]);
+        state.new_todo = "eggs #sh".to_string();
+        assert_eq!(state.tag_completions(), Vector::unit("shop".to_string()));
+
+        state.complete_tag("shop");
+        assert_eq!(state.new_todo, "eggs #shop ");
+        assert!(state.tag_completions().is_empty());
+    }
+
+    #[test]
+    fn completing_a_recurring_todo_schedules_the_next_one() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        let mut state = state_with(&["bins due:2024-05-06 repeat:FREQ=WEEKLY;BYDAY=MO,TH", "milk"]);
+        let id = state.todos[0].id;
+        state.todos[0].done = true;
+
+        state.schedule_next(&id, date(6));
+        assert_eq!(texts(&state), ["bins due:2024-05-09 repeat:FREQ=WEEKLY;BYDAY=MO,TH", "bins due:2024-05-06", "milk"]);
+        assert_eq!(state.todos[0].due, Some(date(9)));
+        assert!(!state.todos[0].done);
+        assert_eq!(state.todos[1].completed_on, Some(date(6)));
+
+        state.todos[1].done = false;
+        state.todos[1].done = true;
+        state.schedule_next(&id, date(6));
+        assert_eq!(state.todos.len(), 3);
+    }
+
+    #[test]
+    fn only_completed_recurring_todos_repeat() {
+        let mut state = state_with(&["bins repeat:FREQ=DAILY", "milk"]);
+        let (bins, milk) = (state.todos[0].id, state.todos[1].id);
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+
+        state.schedule_next(&bins, today);
+        state.todos[1].done = true;
+        state.schedule_next(&milk, today);
+        assert_eq!(state.todos.len(), 2);
+    }
+
+    #[test]
+    fn toggle_all_schedules_recurring_todos() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        let mut state = state_with(&["bins due:2024-05-06 repeat:FREQ=WEEKLY;BYDAY=MO", "milk", "plants repeat:FREQ=DAILY"]);
+        // Completed before, so it isn't repeated again
+        state.todos[2].done = true;
+
+        state.toggle_all_todos(date(6));
+        assert_eq!(texts(&state), [
+            "bins due:2024-05-13 repeat:FREQ=WEEKLY;BYDAY=MO",
+            "bins due:2024-05-06",
+            "milk",
+            "plants repeat:FREQ=DAILY",
+        ]);
+        assert!(!state.todos[0].done);
+        assert!(state.todos.iter().skip(1).all(|todo| todo.done));
+        assert_eq!(state.todos[1].completed_on, Some(date(6)));
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Add(String),
+        /// Clicking a checkbox, which flips `done` and then asks for a rebuild
+        Toggle(usize),
+        ToggleAll,
+        ClearCompleted,
+        SetFilter(Filter),
+        Select(usize),
+        /// Finishing the edit of the selected todo
+        Unselect,
+        Delete(usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            "[a-z #]{0,8}".prop_map(Op::Add),
+            any::<usize>().prop_map(Op::Toggle),
+            Just(Op::ToggleAll),
+            Just(Op::ClearCompleted),
+            prop::sample::select(Filter::ALL.to_vec()).prop_map(Op::SetFilter),
+            any::<usize>().prop_map(Op::Select),
+            Just(Op::Unselect),
+            any::<usize>().prop_map(Op::Delete),
+        ]
+    }
+
+    fn apply(state: &mut AppState, delegate: &mut Delegate, op: Op) {
+        let pick = |state: &AppState, n: usize| {
+            (!state.todos.is_empty()).then(|| state.todos[n % state.todos.len()].id)
+        };
+
+        match op {
+            Op::Add(text) => {
+                state.new_todo = text;
+                state.add_todo();
+            }
+            Op::Toggle(n) => {
+                if let Some(id) = pick(state, n) {
+                    let todo = state.todos.iter_mut().find(|todo| todo.id == id).unwrap();
+                    todo.done = !todo.done;
+                    assert!(delegate.handle_command(&REBUILD.with(id), state).is_handled());
+                }
+            }
+            Op::ToggleAll => state.toggle_all_todos(Local::now().date_naive()),
+            Op::ClearCompleted => state.remove_completed(),
+            Op::SetFilter(filter) => state.set_filter(filter),
+            Op::Select(n) => {
+                if let Some(id) = pick(state, n) {
+                    delegate.handle_command(&SELECT.with(id), state);
+                }
+            }
+            Op::Unselect => {
+                if let Some(id) = state.selected {
+                    delegate.handle_command(&UNSELECT.with(id), state);
+                }
+            }
+            Op::Delete(n) => {
+                if let Some(id) = pick(state, n) {
+                    delegate.handle_command(&DELETE.with(id), state);
+                }
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn filtered_ids_and_selection_stay_consistent(ops in prop::collection::vec(op(), 0..40)) {
+            let mut state = AppState::new();
+            let mut delegate = Delegate::new(None);
+
+            for op in ops {
+                apply(&mut state, &mut delegate, op);
+
+                prop_assert_eq!(&state.filtered_ids, &expected_filtered(&state));
+                if let Some(id) = state.selected {
+                    prop_assert!(state.todos.iter().any(|todo| todo.id == id), "selected todo was deleted");
+                }
+            }
         }
     }
 }
@@ -1,18 +1,31 @@
-use druid::{AppDelegate, Command, DelegateCtx, Env, Handled, Target};
+use chrono::{Local, NaiveDate};
+use druid::{commands, AppDelegate, Command, DelegateCtx, Env, Handled, Target};
 
-use crate::data::{AppState, DELETE, REBUILD, SELECT, UNSELECT};
+use crate::data::{AppState, COMPLETE_TAG, DELETE, FILTER_TAG, REBUILD, SAVE, SELECT, UNSELECT};
+use crate::storage::{self, Autosave};
 
-pub struct Delegate;
+pub struct Delegate {
+    autosave: Option<Autosave>,
+}
 
-impl AppDelegate<AppState> for Delegate {
-    fn command(
-        &mut self,
-        _ctx: &mut DelegateCtx,
-        _target: Target,
-        cmd: &Command,
-        data: &mut AppState,
-        _env: &Env,
-    ) -> Handled {
+impl Delegate {
+    pub fn new(autosave: Option<Autosave>) -> Self {
+        Self { autosave }
+    }
+
+    fn save(&self, data: &AppState) {
+        if let Some(autosave) = &self.autosave {
+            autosave.schedule(&data.todos);
+        }
+    }
+
+    /// Everything `command` does, none of which needs the context, so it also runs without a window.
+    pub fn handle_command(&mut self, cmd: &Command, data: &mut AppState) -> Handled {
+        self.handle_command_on(cmd, data, Local::now().date_naive())
+    }
+
+    /// [`handle_command`](Self::handle_command) as if it was `today`.
+    pub fn handle_command_on(&mut self, cmd: &Command, data: &mut AppState, today: NaiveDate) -> Handled {
         if let Some(id) = cmd.get(SELECT) {
             data.selected = Some(id.clone());
             for todo in data.todos.iter_mut() {
@@ -22,6 +35,8 @@ impl AppDelegate<AppState> for Delegate {
                     todo.lose_selection();
                 }
             }
+            // Selecting another todo commits whatever edit was in progress
+            self.save(data);
             Handled::Yes
         } else if let Some(id) = cmd.get(UNSELECT) {
             data.selected = None;
@@ -30,20 +45,176 @@ impl AppDelegate<AppState> for Delegate {
                     todo.lose_selection();
                 }
             }
+            // The edit may have added or removed tags
+            data.update_filtered();
+            self.save(data);
             Handled::Yes
         } else if let Some(id) = cmd.get(REBUILD) {
             for todo in data.todos.iter_mut() {
                 if id == &todo.id {
                     todo.rebuild();
+                    todo.stamp_completion(today);
                 }
             }
+            data.schedule_next(id, today);
+            // A checked todo leaves the Active view, and so on
+            data.update_filtered();
+            self.save(data);
             Handled::Yes
         } else if let Some(id) = cmd.get(DELETE) {
             data.remove_todo(id);
+            self.save(data);
+            Handled::Yes
+        } else if let Some(tag) = cmd.get(FILTER_TAG) {
+            data.toggle_tag_filter(tag);
+            Handled::Yes
+        } else if let Some(tag) = cmd.get(COMPLETE_TAG) {
+            data.complete_tag(tag);
+            Handled::Yes
+        } else if cmd.is(SAVE) {
+            self.save(data);
+            Handled::Yes
+        } else if let Some(file) = cmd.get(commands::OPEN_FILE) {
+            match storage::import_todotxt(file.path()) {
+                Ok(todos) => {
+                    data.set_todos(todos);
+                    self.save(data);
+                }
+                Err(e) => eprintln!("failed to open {}: {}", file.path().display(), e),
+            }
+            Handled::Yes
+        } else if let Some(file) = cmd.get(commands::SAVE_FILE_AS) {
+            if let Err(e) = storage::export_todotxt(file.path(), &data.todos) {
+                eprintln!("failed to save {}: {}", file.path().display(), e);
+            }
             Handled::Yes
         } else {
-            println!("cmd forwarded: {:?}", cmd);
             Handled::No
         }
     }
 }
+
+impl AppDelegate<AppState> for Delegate {
+    fn command(
+        &mut self,
+        _ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        data: &mut AppState,
+        _env: &Env,
+    ) -> Handled {
+        self.handle_command(cmd, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::im::Vector;
+    use todo_core::todotxt::TodoTxtTask;
+    use todo_core::Filter;
+
+    use super::*;
+    use crate::data::TodoItem;
+
+    fn state_with(texts: &[&str]) -> AppState {
+        AppState::with_todos(texts.iter().map(|text| TodoItem::new(text)).collect())
+    }
+
+    #[test]
+    fn select_starts_editing_only_that_todo() {
+        let mut state = state_with(&["a", "b"]);
+        let mut delegate = Delegate::new(None);
+        let (a, b) = (state.todos[0].id, state.todos[1].id);
+
+        assert!(delegate.handle_command(&SELECT.with(a), &mut state).is_handled());
+        assert_eq!(state.selected, Some(a));
+        assert!(state.todos[0].selected && state.todos[0].editing);
+
+        delegate.handle_command(&SELECT.with(b), &mut state);
+        assert_eq!(state.selected, Some(b));
+        assert!(!state.todos[0].selected && !state.todos[0].editing);
+        assert!(state.todos[1].selected);
+    }
+
+    #[test]
+    fn unselect_ends_the_edit() {
+        let mut state = state_with(&["a"]);
+        let mut delegate = Delegate::new(None);
+        let id = state.todos[0].id;
+
+        delegate.handle_command(&SELECT.with(id), &mut state);
+        delegate.handle_command(&UNSELECT.with(id), &mut state);
+
+        assert_eq!(state.selected, None);
+        assert!(!state.todos[0].selected && !state.todos[0].editing);
+    }
+
+    #[test]
+    fn rebuild_after_checking_a_todo_updates_the_active_view() {
+        let mut state = state_with(&["a", "b"]);
+        let mut delegate = Delegate::new(None);
+        state.set_filter(Filter::Active);
+
+        state.todos[0].done = true;
+        delegate.handle_command(&REBUILD.with(state.todos[0].id), &mut state);
+
+        assert_eq!(state.filtered_ids, Vector::unit(state.todos[1].id));
+    }
+
+    #[test]
+    fn checking_a_todo_dates_it_for_todotxt() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let mut state = state_with(&["a"]);
+        let mut delegate = Delegate::new(None);
+        let id = state.todos[0].id;
+        let created = state.todos[0].created;
+
+        state.todos[0].done = true;
+        delegate.handle_command_on(&REBUILD.with(id), &mut state, today);
+        assert_eq!(state.todos[0].completed_on, Some(today));
+
+        // Without a completion date the creation date couldn't be written
+        let line = TodoTxtTask::from(&state.todos[0]).to_string();
+        let restored = TodoItem::from(TodoTxtTask::parse(&line).unwrap());
+        assert!(restored.done);
+        assert_eq!(restored.created, created);
+        assert_eq!(restored.completed_on, state.todos[0].completed_on);
+
+        state.todos[0].done = false;
+        delegate.handle_command_on(&REBUILD.with(id), &mut state, today);
+        assert_eq!(state.todos[0].completed_on, None);
+    }
+
+    #[test]
+    fn delete_removes_the_todo() {
+        let mut state = state_with(&["a", "b"]);
+        let mut delegate = Delegate::new(None);
+        let id = state.todos[0].id;
+
+        assert!(delegate.handle_command(&DELETE.with(id), &mut state).is_handled());
+        assert_eq!(state.todos.len(), 1);
+        assert!(state.todos.iter().all(|todo| todo.id != id));
+    }
+
+    #[test]
+    fn delete_of_the_selected_todo_clears_selection_and_view() {
+        let mut state = state_with(&["a", "b"]);
+        let mut delegate = Delegate::new(None);
+        let id = state.todos[1].id;
+
+        delegate.handle_command(&SELECT.with(id), &mut state);
+        delegate.handle_command(&DELETE.with(id), &mut state);
+
+        assert_eq!(state.selected, None);
+        assert_eq!(state.filtered_ids, Vector::unit(state.todos[0].id));
+    }
+
+    #[test]
+    fn unknown_commands_are_passed_on() {
+        let mut state = state_with(&[]);
+        let mut delegate = Delegate::new(None);
+        let other = druid::Selector::<()>::new("test.other");
+
+        assert!(!delegate.handle_command(&other.with(()), &mut state).is_handled());
+    }
+}
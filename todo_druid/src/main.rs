@@ -4,19 +4,34 @@ mod controllers;
 mod double_click;
 mod data;
 mod delegate;
+mod schedule;
+mod storage;
+mod tags;
 mod view;
 
 use data::AppState;
 use delegate::Delegate;
-use view::build_ui;
+use storage::Autosave;
+use view::{build_menu, build_ui};
 
 pub fn main() {
     let main_window = WindowDesc::new(build_ui())
+        .menu(build_menu)
         .title("Druid app")
         .window_size((400.0, 400.0));
 
+    let path = storage::data_file();
+    let state = match path.as_deref().map(storage::load) {
+        Some(Ok(todos)) => AppState::with_todos(todos),
+        Some(Err(e)) => {
+            eprintln!("failed to load saved todos: {}", e);
+            AppState::new()
+        }
+        None => AppState::new(),
+    };
+
     AppLauncher::with_window(main_window)
-        .delegate(Delegate {})
-        .launch(AppState::new())
+        .delegate(Delegate::new(path.map(Autosave::new)))
+        .launch(state)
         .expect("Failed to launch application");
 }
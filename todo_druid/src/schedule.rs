@@ -0,0 +1,62 @@
+//! `due:2024-05-06` and `repeat:FREQ=WEEKLY;BYDAY=MO` words in a todo's text.
+//!
+//! Like `#tags` they stay part of the text, so they're edited like the rest of it and
+//! survive a todo.txt round trip.
+
+use std::ops::Range;
+
+use chrono::NaiveDate;
+use todo_core::Recurrence;
+
+const DUE: &str = "due:";
+const REPEAT: &str = "repeat:";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Words of `text` with their byte ranges, not counting the whitespace after them.
+fn words(text: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut offset = 0;
+    text.split_inclusive(char::is_whitespace).map(move |word| {
+        let start = offset;
+        offset += word.len();
+        let trimmed = word.trim_end();
+        (start..start + trimmed.len(), trimmed)
+    })
+}
+
+pub fn due_date(text: &str) -> Option<NaiveDate> {
+    words(text)
+        .filter_map(|(_, word)| word.strip_prefix(DUE))
+        .find_map(|date| NaiveDate::parse_from_str(date, DATE_FORMAT).ok())
+}
+
+pub fn recurrence(text: &str) -> Option<Recurrence> {
+    words(text)
+        .filter_map(|(_, word)| word.strip_prefix(REPEAT))
+        .find_map(|rule| rule.parse().ok())
+}
+
+/// Byte ranges of the `due:` and `repeat:` words, to set them apart from the description.
+pub fn schedule_spans(text: &str) -> Vec<Range<usize>> {
+    words(text)
+        .filter(|(_, word)| word.starts_with(DUE) || word.starts_with(REPEAT))
+        .map(|(range, _)| range)
+        .collect()
+}
+
+/// `text` due on `date`, replacing the `due:` word it already had.
+pub fn with_due(text: &str, date: NaiveDate) -> String {
+    let due = format!("{}{}", DUE, date.format(DATE_FORMAT));
+    match words(text).find(|(_, word)| word.starts_with(DUE)) {
+        Some((range, _)) => format!("{}{}{}", &text[..range.start], due, &text[range.end..]),
+        None => format!("{} {}", text.trim_end(), due),
+    }
+}
+
+/// `text` with its `repeat:` words taken out.
+pub fn without_recurrence(text: &str) -> String {
+    words(text)
+        .map(|(_, word)| word)
+        .filter(|word| !word.is_empty() && !word.starts_with(REPEAT))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
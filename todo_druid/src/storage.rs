@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use druid::im::Vector;
+use todo_core::json::{self, StoredTask};
+use todo_core::todotxt::{self, TodoTxtTask};
+use uuid::Uuid;
+
+use crate::data::TodoItem;
+
+/// How long the list has to stay unchanged before it's written out.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+impl From<&TodoItem> for StoredTask {
+    fn from(todo: &TodoItem) -> Self {
+        Self {
+            id: todo.id,
+            description: todo.description().to_string(),
+            completed: todo.done,
+            priority: todo.priority,
+            created: todo.created,
+            completed_on: todo.completed_on,
+        }
+    }
+}
+
+impl From<StoredTask> for TodoItem {
+    fn from(stored: StoredTask) -> Self {
+        let mut todo = TodoItem::restore(stored.id, &stored.description, stored.completed);
+        todo.priority = stored.priority;
+        todo.created = stored.created;
+        todo.completed_on = stored.completed_on;
+        todo
+    }
+}
+
+impl From<&TodoItem> for TodoTxtTask {
+    fn from(todo: &TodoItem) -> Self {
+        Self {
+            completed: todo.done,
+            priority: todo.priority,
+            completion_date: todo.completed_on,
+            creation_date: todo.created,
+            description: todo.description().to_string(),
+        }
+    }
+}
+
+impl From<TodoTxtTask> for TodoItem {
+    fn from(task: TodoTxtTask) -> Self {
+        let mut todo = TodoItem::restore(Uuid::new_v4(), &task.description, task.completed);
+        todo.priority = task.priority;
+        todo.created = task.creation_date;
+        todo.completed_on = task.completion_date;
+        todo
+    }
+}
+
+/// Location of the todo list. `$TODO_FILE` overrides it, same as for the `todo` CLI.
+pub fn data_file() -> Option<PathBuf> {
+    json::default_path()
+}
+
+/// Reads the todo list, treating a missing file as an empty list.
+pub fn load(path: &Path) -> io::Result<Vector<TodoItem>> {
+    Ok(json::load(path)?.into_iter().map(TodoItem::from).collect())
+}
+
+pub fn import_todotxt(path: &Path) -> io::Result<Vector<TodoItem>> {
+    let text = fs::read_to_string(path)?;
+    Ok(todotxt::parse(&text).into_iter().map(TodoItem::from).collect())
+}
+
+pub fn export_todotxt(path: &Path, todos: &Vector<TodoItem>) -> io::Result<()> {
+    let tasks = todos.iter().map(TodoTxtTask::from).collect::<Vec<_>>();
+    fs::write(path, todotxt::write(&tasks))
+}
+
+/// Saves snapshots of the list on a background thread once changes settle down.
+pub struct Autosave {
+    tx: Option<Sender<Vec<StoredTask>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Autosave {
+    pub fn new(path: PathBuf) -> Self {
+        let (tx, rx) = mpsc::channel::<Vec<StoredTask>>();
+
+        let worker = thread::spawn(move || {
+            while let Ok(mut latest) = rx.recv() {
+                let mut closed = false;
+                loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(newer) => latest = newer,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            closed = true;
+                            break;
+                        }
+                    }
+                }
+
+                if let Err(e) = json::save(&path, &latest) {
+                    eprintln!("failed to save todos to {}: {}", path.display(), e);
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            tx: Some(tx),
+            worker: Some(worker),
+        }
+    }
+
+    pub fn schedule(&self, todos: &Vector<TodoItem>) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(todos.iter().map(StoredTask::from).collect());
+        }
+    }
+}
+
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        // Closing the channel makes the worker flush whatever is still pending
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::ops::Range;
+
+use druid::im::Vector;
+use druid::Color;
+
+const PALETTE: [Color; 8] = [
+    Color::rgb8(0xD3, 0x3F, 0x49),
+    Color::rgb8(0xE0, 0x7A, 0x1F),
+    Color::rgb8(0xB5, 0x9A, 0x00),
+    Color::rgb8(0x3B, 0x9B, 0x4A),
+    Color::rgb8(0x1F, 0x8A, 0x99),
+    Color::rgb8(0x34, 0x6E, 0xC9),
+    Color::rgb8(0x7A, 0x4F, 0xC9),
+    Color::rgb8(0xC0, 0x4F, 0x9E),
+];
+
+/// Byte ranges of `#tag` words in `text`, along with the tag name.
+pub fn tag_spans(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for word in text.split_inclusive(char::is_whitespace) {
+        let trimmed = word.trim_end();
+        if let Some(name) = trimmed.strip_prefix('#').filter(|name| is_tag_name(name)) {
+            spans.push((offset..offset + trimmed.len(), name));
+        }
+        offset += word.len();
+    }
+    spans
+}
+
+/// Distinct tags in `text`, lowercased, in order of appearance.
+pub fn extract_tags(text: &str) -> Vector<String> {
+    let mut tags = Vector::new();
+    for (_, name) in tag_spans(text) {
+        let name = name.to_lowercase();
+        if !tags.contains(&name) {
+            tags.push_back(name);
+        }
+    }
+    tags
+}
+
+/// The partially typed tag at the end of `text`, if the last word is one.
+pub fn tag_prefix(text: &str) -> Option<&str> {
+    let last = text.rsplit(char::is_whitespace).next()?;
+    last.strip_prefix('#').filter(|name| name.is_empty() || is_tag_name(name))
+}
+
+/// A stable colour per tag, so the same tag looks the same everywhere.
+pub fn tag_color(name: &str) -> Color {
+    let hash = name.to_lowercase().bytes().fold(0usize, |hash, b| hash.wrapping_mul(31).wrapping_add(b as usize));
+    PALETTE[hash % PALETTE.len()]
+}
+
+fn is_tag_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
@@ -1,10 +1,12 @@
 use druid::{theme::*, widget::Painter, widget::Scroll, widget::{Button, Checkbox, Either, Flex, List, RawLabel, TextBox}, Color, Insets, RenderContext, Widget, WidgetExt, LensExt, lens, UnitPoint};
+use druid::{commands, Env, FileDialogOptions, FileSpec, Menu, MenuItem, WindowId};
 use druid::im::Vector;
 use druid::widget::{DisabledIf, Label};
 
 use crate::controllers::{AddTodoController, TodoItemController};
-use crate::data::{AppState, TodoItem};
+use crate::data::{AppState, TagChip, TodoItem, COMPLETE_TAG, FILTER_TAG};
 use crate::double_click::DoubleClick;
+use crate::tags::tag_color;
 
 pub fn todo_item() -> impl Widget<TodoItem> {
     let painter = Painter::new(move |ctx, data: &TodoItem, env| {
@@ -69,6 +71,30 @@ pub fn todo_item() -> impl Widget<TodoItem> {
         .on_click(TodoItem::select)
 }
 
+pub fn tag_chip() -> impl Widget<TagChip> {
+    let painter = Painter::new(|ctx, data: &TagChip, _env| {
+        let bounds = ctx.size().to_rect().inset(-2.).to_rounded_rect(10.);
+        let color = tag_color(&data.name);
+        if data.selected {
+            ctx.fill(bounds, &color);
+        } else {
+            ctx.stroke(bounds, &color, 1.5);
+        }
+    });
+
+    Label::dynamic(|data: &TagChip, _| format!("#{}", data.name))
+        .padding((8., 4.))
+        .env_scope(|env, data: &TagChip| {
+            let color = if data.selected { Color::WHITE } else { tag_color(&data.name) };
+            env.set(TEXT_COLOR, color);
+        })
+        .background(painter)
+        .on_click(|ctx, data: &mut TagChip, _env| {
+            ctx.submit_command(FILTER_TAG.with(data.name.clone()));
+        })
+        .padding(2.)
+}
+
 pub fn build_ui() -> impl Widget<AppState> {
     let title = Label::new("todos")
         .with_text_size(TEXT_SIZE_LARGE)
@@ -104,16 +130,27 @@ pub fn build_ui() -> impl Widget<AppState> {
         .padding(10.)
         .background(Color::WHITE);
 
+    let tag_completions = List::new(|| {
+        Button::dynamic(|tag: &String, _| format!("#{}", tag))
+            .on_click(|ctx, tag: &mut String, _env| {
+                ctx.submit_command(COMPLETE_TAG.with(tag.clone()));
+            })
+            .padding(2.)
+    })
+        .horizontal()
+        .padding((10., 0.))
+        .lens(lens::Identity.map(|d: &AppState| d.tag_completions(), |_, _| {}));
+
+    let tag_chips = List::new(tag_chip)
+        .horizontal()
+        .padding((10., 0.))
+        .lens(lens::Identity.map(|d: &AppState| d.tag_chips(), |_, _| {}));
+
     let todo_list = List::new(todo_item)
         .with_spacing(5.)
         .padding(10.)
         .lens(lens::Identity.map(
-            |d: &AppState| d.todos
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(idx, todo)| d.filtered_ids.contains(&idx).then_some(todo))
-                    .cloned()
-                    .collect(),
+            |d: &AppState| d.filtered_todos(),
             |d: &mut AppState, list: Vector<TodoItem>| {
                 for todo in list {
                     if let Some(d) = d.todos.iter_mut().find(|x| x.id == todo.id) {
@@ -124,10 +161,7 @@ pub fn build_ui() -> impl Widget<AppState> {
         ));
 
     let task_count = Label::dynamic(|data: &AppState, _| {
-        let count = data.todos.iter()
-            .filter(|todo| !todo.done)
-            .count();
-        format!("{} {}", count, if count == 1 { "task" } else { "tasks" })
+        todo_core::tasks_left_label(data.active_count())
     })
         .padding(10.)
         .env_scope(|env, _data| {
@@ -174,10 +208,27 @@ pub fn build_ui() -> impl Widget<AppState> {
             Flex::column()
                 .with_child(title)
                 .with_child(create)
+                .with_child(tag_completions)
                 .with_child(actions_row)
+                .with_child(tag_chips)
                 .with_flex_child(Scroll::new(todo_list).vertical(), 1.)
                 .padding((15., 30., 15., 30.)),
             1.,
         )
         .background(Color::WHITE)
 }
+
+pub fn build_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<AppState> {
+    let todo_txt = FileSpec::new("todo.txt", &["txt"]);
+
+    let open = FileDialogOptions::new()
+        .allowed_types(vec![todo_txt])
+        .default_type(todo_txt);
+    let save = open.clone().default_name("todo.txt");
+
+    let file = Menu::new("File")
+        .entry(MenuItem::new("Open todo.txt…").command(commands::SHOW_OPEN_PANEL.with(open)))
+        .entry(MenuItem::new("Save as todo.txt…").command(commands::SHOW_SAVE_PANEL.with(save)));
+
+    Menu::empty().entry(file)
+}
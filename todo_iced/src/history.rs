@@ -0,0 +1,126 @@
+/// Undo/redo stack of snapshots.
+///
+/// Each recorded step stores the state from *before* a change. Steps recorded with the same
+/// group key back to back are merged into one, so typing a word is undone in a single step.
+#[derive(Debug)]
+pub struct History<T, G = usize> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    group: Option<G>,
+    limit: usize,
+}
+
+impl<T: Clone, G: PartialEq> History<T, G> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            group: None,
+            limit,
+        }
+    }
+
+    /// Remembers `before` as the state to go back to, unless it continues the current group.
+    pub fn record(&mut self, before: &T, group: Option<G>) {
+        self.redo.clear();
+
+        if group.is_some() && group == self.group {
+            return;
+        }
+        self.group = group;
+
+        if self.undo.len() == self.limit {
+            self.undo.remove(0);
+        }
+        self.undo.push(before.clone());
+    }
+
+    /// Ends the current group, so the next change starts a new step.
+    pub fn seal(&mut self) {
+        self.group = None;
+    }
+
+    pub fn undo(&mut self, current: &T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current.clone());
+        self.group = None;
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: &T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.clone());
+        self.group = None;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies each change to `state` the way the app does: record, then change.
+    fn change(history: &mut History<String, &'static str>, state: &mut String, to: &str, group: Option<&'static str>) {
+        history.record(state, group);
+        *state = to.to_string();
+    }
+
+    #[test]
+    fn consecutive_edits_in_a_group_undo_together() {
+        let mut history = History::new(10);
+        let mut state = String::new();
+        change(&mut history, &mut state, "h", Some("typing"));
+        change(&mut history, &mut state, "hi", Some("typing"));
+        change(&mut history, &mut state, "hi!", None);
+
+        state = history.undo(&state).unwrap();
+        assert_eq!(state, "hi");
+        state = history.undo(&state).unwrap();
+        assert_eq!(state, "");
+        assert_eq!(history.undo(&state), None);
+    }
+
+    #[test]
+    fn sealing_starts_a_new_step() {
+        let mut history = History::new(10);
+        let mut state = String::new();
+        change(&mut history, &mut state, "a", Some("typing"));
+        history.seal();
+        change(&mut history, &mut state, "ab", Some("typing"));
+
+        state = history.undo(&state).unwrap();
+        assert_eq!(state, "a");
+    }
+
+    #[test]
+    fn redo_goes_forward_until_something_new_changes() {
+        let mut history = History::new(10);
+        let mut state = String::new();
+        change(&mut history, &mut state, "a", None);
+        change(&mut history, &mut state, "b", None);
+
+        state = history.undo(&state).unwrap();
+        state = history.redo(&state).unwrap();
+        assert_eq!(state, "b");
+
+        state = history.undo(&state).unwrap();
+        change(&mut history, &mut state, "c", None);
+        assert_eq!(history.redo(&state), None);
+        state = history.undo(&state).unwrap();
+        assert_eq!(state, "a");
+    }
+
+    #[test]
+    fn oldest_steps_are_dropped_beyond_the_limit() {
+        let mut history = History::new(2);
+        let mut state = String::new();
+        for to in ["a", "b", "c"] {
+            change(&mut history, &mut state, to, None);
+        }
+
+        state = history.undo(&state).unwrap();
+        state = history.undo(&state).unwrap();
+        assert_eq!(state, "a");
+        assert_eq!(history.undo(&state), None);
+    }
+}
@@ -1,11 +1,27 @@
 use iced::alignment::{self, Alignment, Horizontal};
 use iced::theme::{self, Theme};
-use iced::widget::{self, button, checkbox, column, container, row, scrollable, text, text_input};
+use std::cmp::Reverse;
+use std::fmt;
+use std::ops::Range;
+
+use iced::widget::{self, button, checkbox, column, container, pick_list, row, scrollable, text, text_input};
 use iced::window;
 use iced::{Application, Element};
 use iced::{Color, Command, Length, Settings, Subscription};
+use iced::event::Status;
 use iced::keyboard::{Event, KeyCode};
+use iced::mouse;
 use iced::subscription::events_with;
+use todo_core::{Completable, Filter};
+use uuid::Uuid;
+
+mod history;
+mod search;
+
+use history::History;
+
+/// How many steps Ctrl+Z can go back.
+const UNDO_LIMIT: usize = 100;
 
 pub fn main() -> iced::Result {
     Todos::run(Settings {
@@ -21,7 +37,10 @@ pub fn main() -> iced::Result {
 struct Todos {
     input_value: String,
     filter: Filter,
+    search: String,
+    sort: SortMode,
     tasks: Vec<Task>,
+    history: History<Vec<Task>, Uuid>,
 }
 
 #[derive(Debug, Clone)]
@@ -29,10 +48,17 @@ enum Message {
     InputChanged(String),
     CreateTask,
     FilterChanged(Filter),
-    TaskMessage(usize, TaskMessage),
+    SearchChanged(String),
+    FocusSearch,
+    SortChanged(SortMode),
+    TaskMessage(Uuid, TaskMessage),
     SelectAll,
     DeleteCompleted,
     TabPressed { shift: bool },
+    CancelEdits,
+    FinishEdits,
+    Undo,
+    Redo,
 }
 
 impl Application for Todos {
@@ -45,13 +71,18 @@ impl Application for Todos {
         (Todos {
             input_value: "".to_string(),
             filter: Filter::All,
+            search: String::new(),
+            sort: SortMode::Created,
             tasks: vec![],
+            history: History::new(UNDO_LIMIT),
         }, Command::none())
     }
 
     fn title(&self) -> String { "Iced app".to_string() }
 
     fn update(&mut self, message: Message) -> Command<Message> {
+        self.record_history(&message);
+
         let command = match message {
             Message::InputChanged(value) => {
                 self.input_value = value;
@@ -68,30 +99,48 @@ impl Application for Todos {
                 self.filter = filter;
                 Command::none()
             }
+            Message::SearchChanged(search) => {
+                self.search = search;
+                Command::none()
+            }
+            Message::FocusSearch => {
+                Command::batch(vec![
+                    text_input::focus(search_input_id()),
+                    text_input::select_all(search_input_id()),
+                ])
+            }
+            Message::SortChanged(sort) => {
+                self.sort = sort;
+                Command::none()
+            }
             Message::SelectAll => {
-                let check = self.tasks.iter().filter(|task| !task.completed).count() > 0;
-                self.tasks.iter_mut().for_each(|task| task.completed = check);
+                todo_core::toggle_all(self.tasks.iter_mut());
                 Command::none()
             }
             Message::DeleteCompleted => {
-                self.tasks = self.tasks.iter()
-                    .filter(|task| !task.completed)
-                    .cloned()
-                    .collect();
+                self.tasks.retain(|task| Filter::Active.matches(task));
+                Command::none()
+            }
+            Message::TaskMessage(id, TaskMessage::Delete) => {
+                self.tasks.retain(|task| task.id != id);
                 Command::none()
             }
-            Message::TaskMessage(i, TaskMessage::Delete) => {
-                self.tasks.remove(i);
+            Message::TaskMessage(id, TaskMessage::FinishEdition) => {
+                self.finish_edit(id);
                 Command::none()
             }
-            Message::TaskMessage(i, task_message) => {
-                if let Some(task) = self.tasks.get_mut(i) {
-                    let should_focus = matches!(task_message, TaskMessage::Edit);
+            Message::TaskMessage(id, task_message) => {
+                let should_focus = matches!(task_message, TaskMessage::Edit);
+                // Only one task is edited at a time
+                if should_focus {
+                    self.finish_edits();
+                }
 
+                if let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) {
                     task.update(task_message);
 
                     if should_focus {
-                        let id = Task::text_input_id(i);
+                        let id = Task::text_input_id(id);
                         Command::batch(vec![
                             text_input::focus(id.clone()),
                             text_input::select_all(id),
@@ -104,12 +153,35 @@ impl Application for Todos {
                 }
             }
             Message::TabPressed { shift } => {
+                self.finish_edits();
                 if shift {
                     widget::focus_previous()
                 } else {
                     widget::focus_next()
                 }
             }
+            Message::CancelEdits => {
+                for task in self.tasks.iter_mut().filter(|task| task.is_editing) {
+                    task.update(TaskMessage::CancelEdit);
+                }
+                Command::none()
+            }
+            Message::FinishEdits => {
+                self.finish_edits();
+                Command::none()
+            }
+            Message::Undo => {
+                if let Some(tasks) = self.history.undo(&self.tasks) {
+                    self.restore(tasks);
+                }
+                Command::none()
+            }
+            Message::Redo => {
+                if let Some(tasks) = self.history.redo(&self.tasks) {
+                    self.restore(tasks);
+                }
+                Command::none()
+            }
         };
 
         Command::batch(vec![command])
@@ -134,17 +206,24 @@ impl Application for Todos {
 
         let input_line = row![check_all, input];
 
-        let controls = view_controls(&self.tasks, self.filter);
-        let filtered_tasks = self.tasks.iter()
-            .filter(|task| self.filter.matches(task));
-
-        let tasks: Option<Element<_>> = (filtered_tasks.count() > 0).then_some(
-            column(self.tasks.iter()
-                .enumerate()
-                .filter(|(_, task)| self.filter.matches(task))
-                .map(|(i, task)| {
-                    task.view(i).map(move |message| {
-                        Message::TaskMessage(i, message)
+        let controls = view_controls(&self.tasks, self.filter, &self.search);
+        let sort = view_sort(self.sort);
+
+        let mut filtered_tasks = self.tasks.iter()
+            .filter(|task| self.filter.matches(*task))
+            .filter(|task| search::matches(&task.description, &self.search))
+            .collect::<Vec<_>>();
+        if self.sort == SortMode::Priority {
+            // Stable sort, so tasks of equal priority stay in creation order
+            filtered_tasks.sort_by_key(|task| Reverse(task.priority));
+        }
+
+        let tasks: Option<Element<_>> = (!filtered_tasks.is_empty()).then(||
+            column(filtered_tasks.into_iter()
+                .map(|task| {
+                    let id = task.id;
+                    task.view(self.search.trim()).map(move |message| {
+                        Message::TaskMessage(id, message)
                     })
                 })
                 .collect())
@@ -153,9 +232,9 @@ impl Application for Todos {
         );
 
         let content = if let Some(tasks) = tasks {
-            column![title, input_line, controls, tasks]
+            column![title, input_line, controls, sort, tasks]
         } else {
-            column![title, input_line, controls]
+            column![title, input_line, controls, sort]
         }
             .spacing(20)
             .max_width(800);
@@ -164,48 +243,129 @@ impl Application for Todos {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        events_with(|evt, _| {
+        events_with(|evt, status| {
             match evt {
                 iced::Event::Keyboard(Event::KeyPressed { key_code, modifiers}) => {
                     match (key_code, modifiers) {
                         (KeyCode::Tab, _) => {
                             Some(Message::TabPressed { shift: modifiers.shift() })
                         },
+                        (KeyCode::Z, _) if modifiers.command() => {
+                            Some(if modifiers.shift() { Message::Redo } else { Message::Undo })
+                        },
+                        (KeyCode::F, _) if modifiers.command() => {
+                            Some(Message::FocusSearch)
+                        },
+                        (KeyCode::Escape, _) => Some(Message::CancelEdits),
                         _ => None,
                     }
                 }
+                // Text inputs don't report losing focus, but clicking anywhere that isn't
+                // another widget moves it away from the task being edited
+                iced::Event::Mouse(mouse::Event::ButtonPressed(_)) if status == Status::Ignored => {
+                    Some(Message::FinishEdits)
+                }
                 _ => None,
             }
         })
     }
 }
 
+impl Todos {
+    /// Snapshots the task list before any message that changes it.
+    fn record_history(&mut self, message: &Message) {
+        match message {
+            Message::CreateTask if self.input_value.is_empty() => {}
+            Message::CreateTask
+            | Message::SelectAll
+            | Message::DeleteCompleted
+            | Message::TaskMessage(_, TaskMessage::Delete)
+            | Message::TaskMessage(_, TaskMessage::Completed(_))
+            | Message::TaskMessage(_, TaskMessage::PriorityChanged(_)) => {
+                self.history.record(&self.tasks, None);
+            }
+            // Consecutive keystrokes in the same task are undone together
+            Message::TaskMessage(id, TaskMessage::DescriptionEdited(_)) => {
+                self.history.record(&self.tasks, Some(*id));
+            }
+            Message::TaskMessage(_, TaskMessage::Edit | TaskMessage::FinishEdition | TaskMessage::CancelEdit)
+            | Message::CancelEdits
+            | Message::FinishEdits => {
+                self.history.seal();
+            }
+            _ => {}
+        }
+    }
+
+    fn restore(&mut self, tasks: Vec<Task>) {
+        self.tasks = tasks;
+        self.tasks.iter_mut().for_each(|task| task.is_editing = false);
+    }
+
+    /// Saves the edited description. Saving an empty one deletes the task, as in TodoMVC.
+    fn finish_edit(&mut self, id: Uuid) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else { return };
+        task.update(TaskMessage::FinishEdition);
+        if task.description.is_empty() {
+            self.tasks.retain(|task| task.id != id);
+        }
+    }
+
+    fn finish_edits(&mut self) {
+        let editing = self.tasks.iter()
+            .filter_map(|task| task.is_editing.then_some(task.id))
+            .collect::<Vec<_>>();
+        for id in editing {
+            self.finish_edit(id);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Task {
+    id: Uuid,
     description: String,
     completed: bool,
+    priority: Priority,
     is_editing: bool,
+    /// The description from before the edit, for Escape to go back to
+    stash: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum TaskMessage {
     Completed(bool),
+    PriorityChanged(Priority),
     Edit,
     DescriptionEdited(String),
     FinishEdition,
+    CancelEdit,
     Delete,
 }
 
+impl Completable for Task {
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
+}
+
 impl Task {
-    fn text_input_id(i: usize) -> text_input::Id {
-        text_input::Id::new(format!("task-{i}"))
+    fn text_input_id(id: Uuid) -> text_input::Id {
+        text_input::Id::new(format!("task-{id}"))
     }
 
     fn new(description: String) -> Self {
         Task {
+            id: Uuid::new_v4(),
             description,
             completed: false,
+            priority: Priority::None,
             is_editing: false,
+            stash: None,
         }
     }
 
@@ -214,26 +374,37 @@ impl Task {
             TaskMessage::Completed(completed) => {
                 self.completed = completed;
             }
+            TaskMessage::PriorityChanged(priority) => {
+                self.priority = priority;
+            }
             TaskMessage::Edit => {
+                self.stash = Some(self.description.clone());
                 self.is_editing = true;
             }
             TaskMessage::DescriptionEdited(new_description) => {
                 self.description = new_description;
             }
             TaskMessage::FinishEdition => {
-                if !self.description.is_empty() {
-                    self.is_editing = false;
+                self.description = self.description.trim().to_string();
+                self.stash = None;
+                self.is_editing = false;
+            }
+            TaskMessage::CancelEdit => {
+                if let Some(description) = self.stash.take() {
+                    self.description = description;
                 }
+                self.is_editing = false;
             }
             TaskMessage::Delete => {}
         }
     }
 
-    fn view(&self, i: usize) -> Element<TaskMessage> {
+    /// Shows the task, with any matches of `query` highlighted.
+    fn view(&self, query: &str) -> Element<TaskMessage> {
         if self.is_editing {
             let text_input =
                 text_input("", &self.description, TaskMessage::DescriptionEdited)
-                    .id(Self::text_input_id(i))
+                    .id(Self::text_input_id(self.id))
                     .on_submit(TaskMessage::FinishEdition)
                     .padding(10);
 
@@ -255,14 +426,37 @@ impl Task {
                 self.description.clone()
             };
 
-            let checkbox = checkbox(
-                &formatted,
-                self.completed,
-                TaskMessage::Completed,
-            ).width(Length::Fill);
+            let matches = search::find(&formatted, query);
+            let checkbox: Element<_> = if matches.is_empty() {
+                checkbox(
+                    &formatted,
+                    self.completed,
+                    TaskMessage::Completed,
+                ).width(Length::Fill).into()
+            } else {
+                row![
+                    checkbox("", self.completed, TaskMessage::Completed),
+                    highlighted(&formatted, &matches),
+                ]
+                    .width(Length::Fill)
+                    .align_items(Alignment::Center)
+                    .into()
+            };
+
+            let marker = text(self.priority.marker())
+                .size(20)
+                .style(self.priority.color());
+
+            let priority = pick_list(
+                &Priority::ALL[..],
+                Some(self.priority),
+                TaskMessage::PriorityChanged,
+            ).padding(8);
 
             row![
+                marker,
                 checkbox,
+                priority,
                 button("edit")
                 .on_press(TaskMessage::Edit)
                 .padding(10)
@@ -275,8 +469,30 @@ impl Task {
     }
 }
 
-fn view_controls(tasks: &[Task], current_filter: Filter) -> Element<Message> {
-    let tasks_left = tasks.iter().filter(|task| !task.completed).count();
+/// `text` with the given byte ranges drawn in the highlight colour.
+fn highlighted<'a>(text_value: &str, ranges: &[Range<usize>]) -> Element<'a, TaskMessage> {
+    let highlight = Color::from([0.85, 0.45, 0.0]);
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for range in ranges {
+        if range.start > last {
+            segments.push(text(text_value[last..range.start].to_string()).into());
+        }
+        segments.push(text(text_value[range.clone()].to_string()).style(highlight).into());
+        last = range.end;
+    }
+    if last < text_value.len() {
+        segments.push(text(text_value[last..].to_string()).into());
+    }
+    row(segments).into()
+}
+
+fn search_input_id() -> text_input::Id {
+    text_input::Id::new("search")
+}
+
+fn view_controls<'a>(tasks: &[Task], current_filter: Filter, search: &str) -> Element<'a, Message> {
+    let tasks_left = todo_core::count_active(tasks);
 
     let filter_button = |label, filter, current_filter| {
         let label = text(label);
@@ -299,15 +515,17 @@ fn view_controls(tasks: &[Task], current_filter: Filter) -> Element<Message> {
         .align_x(Horizontal::Right);
 
     row![
-        text(format!("{tasks_left} {}", if tasks_left == 1 { "task" } else { "tasks" }))
+        text(todo_core::tasks_left_label(tasks_left))
             .width(Length::Fill),
-        row![
-            filter_button("All", Filter::All, current_filter),
-            filter_button("Active", Filter::Active, current_filter),
-            filter_button("Completed", Filter::Completed, current_filter,),
-        ]
+        row(Filter::ALL.into_iter()
+            .map(|filter| filter_button(filter.label(), filter, current_filter).into())
+            .collect())
             .width(Length::Shrink)
             .spacing(10),
+        text_input("Search (Ctrl+F)", search, Message::SearchChanged)
+            .id(search_input_id())
+            .padding(8)
+            .width(Length::Fixed(150.)),
         clear_cont.width(Length::Fill),
     ]
         .spacing(20)
@@ -315,15 +533,68 @@ fn view_controls(tasks: &[Task], current_filter: Filter) -> Element<Message> {
         .into()
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Filter { All, Active, Completed }
+fn view_sort(current_sort: SortMode) -> Element<'static, Message> {
+    let selector = pick_list(&SortMode::ALL[..], Some(current_sort), Message::SortChanged)
+        .padding(8);
 
-impl Filter {
-    fn matches(self, task: &Task) -> bool {
+    container(
+        row![text("Sort by"), selector]
+            .spacing(10)
+            .align_items(Alignment::Center)
+    )
+        .width(Length::Fill)
+        .align_x(Horizontal::Right)
+        .into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority { None, Low, Medium, High }
+
+impl Priority {
+    const ALL: [Priority; 4] = [Priority::None, Priority::Low, Priority::Medium, Priority::High];
+
+    fn marker(self) -> &'static str {
         match self {
-            Filter::All => true,
-            Filter::Active => !task.completed,
-            Filter::Completed => task.completed,
+            Priority::None => "",
+            Priority::Low => "!",
+            Priority::Medium => "!!",
+            Priority::High => "!!!",
         }
     }
+
+    fn color(self) -> Color {
+        match self {
+            Priority::None => Color::TRANSPARENT,
+            Priority::Low => Color::from([0.4, 0.6, 0.9]),
+            Priority::Medium => Color::from([0.9, 0.6, 0.1]),
+            Priority::High => Color::from([0.8, 0.2, 0.2]),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Priority::None => "No priority",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode { Created, Priority }
+
+impl SortMode {
+    const ALL: [SortMode; 2] = [SortMode::Created, SortMode::Priority];
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortMode::Created => "Creation order",
+            SortMode::Priority => "Priority",
+        })
+    }
 }
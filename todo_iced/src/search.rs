@@ -0,0 +1,99 @@
+//! Case- and diacritic-insensitive substring search, so "cafe" finds "Café".
+
+use std::ops::Range;
+
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// Byte ranges in `haystack` where `query` matches, without overlaps.
+pub fn find(haystack: &str, query: &str) -> Vec<Range<usize>> {
+    let query = fold(query).map(|(c, _)| c).collect::<Vec<_>>();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let folded = fold(haystack).collect::<Vec<_>>();
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut i = 0;
+    while i + query.len() <= folded.len() {
+        let window = &folded[i..i + query.len()];
+        if window.iter().map(|(c, _)| c).eq(query.iter()) {
+            let start = window[0].1.start;
+            let end = window[window.len() - 1].1.end;
+            // Characters that fold to several chars can be shared by adjacent matches
+            match ranges.last_mut() {
+                Some(last) if last.end > start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+pub fn matches(haystack: &str, query: &str) -> bool {
+    query.trim().is_empty() || !find(haystack, query.trim()).is_empty()
+}
+
+/// Lowercased characters with accents stripped, each paired with the byte range of the
+/// character in `text` it came from.
+fn fold(text: &str) -> impl Iterator<Item = (char, Range<usize>)> + '_ {
+    text.char_indices().flat_map(|(start, c)| {
+        let range = start..start + c.len_utf8();
+        let mut base = Vec::new();
+        decompose_canonical(c, |part| {
+            if !is_combining_mark(part) {
+                base.extend(part.to_lowercase());
+            }
+        });
+        base.into_iter().map(move |c| (c, range.clone()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTS: &[&str] = &["Café au lait", "Crème brûlée", "ÉCOLE", "İstanbul", "Straße", "日本語のメモ", "e\u{301}clair"];
+
+    #[test]
+    fn ignores_case_and_accents() {
+        assert_eq!(find("Café au lait", "CAFE"), vec![0..5]);
+        assert_eq!(find("Crème brûlée", "brulee"), vec![7..15]);
+        assert_eq!(find("e\u{301}clair", "ecl"), vec![0..5]);
+        assert!(matches("ÉCOLE", "école"));
+        assert!(!matches("Café", "cafes"));
+    }
+
+    #[test]
+    fn blank_queries_match_everything_and_highlight_nothing() {
+        assert!(matches("anything", "  "));
+        assert!(find("anything", "").is_empty());
+    }
+
+    #[test]
+    fn adjacent_matches_do_not_overlap() {
+        assert_eq!(find("aaaa", "aa"), [0..2, 2..4]);
+        assert_eq!(find("ÀàÀ", "a"), [0..2, 2..4, 4..6]);
+    }
+
+    #[test]
+    fn highlights_always_fall_on_char_boundaries() {
+        for text in TEXTS {
+            // Every piece of the text, as typed without its accents
+            let folded = fold(text).map(|(c, _)| c).collect::<String>();
+            let chars = folded.chars().collect::<Vec<_>>();
+            for start in 0..chars.len() {
+                for end in start + 1..=chars.len() {
+                    let query = chars[start..end].iter().collect::<String>();
+                    let ranges = find(text, &query);
+                    assert!(!ranges.is_empty(), "{query:?} in {text:?}");
+                    for range in ranges {
+                        assert!(text.get(range.clone()).is_some(), "{range:?} of {text:?}");
+                    }
+                }
+            }
+        }
+    }
+}
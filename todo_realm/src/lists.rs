@@ -0,0 +1,115 @@
+use gtk::prelude::*;
+use relm4::gtk::Align;
+use relm4::prelude::*;
+
+use crate::storage::ListRecord;
+use crate::AppMsg;
+
+/// A named list in the sidebar.
+pub(crate) struct ListRow {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    active_count: usize,
+    is_renaming: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ListRowInput {
+    SetCount(usize),
+    StartRename,
+    Rename(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ListRowOutput {
+    Renamed(i64, String),
+}
+
+#[relm4::factory(pub(crate))]
+impl FactoryComponent for ListRow {
+    /// A list with its number of unfinished tasks
+    type Init = (ListRecord, usize);
+    type Input = ListRowInput;
+    type Output = ListRowOutput;
+    type CommandOutput = ();
+    type ParentInput = AppMsg;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Horizontal,
+            set_spacing: 6,
+            set_margin_all: 6,
+
+            gtk::Label {
+                #[watch]
+                set_visible: !self.is_renaming,
+                #[watch]
+                set_label: &self.name,
+                set_hexpand: true,
+                set_halign: Align::Start,
+                set_ellipsize: gtk::pango::EllipsizeMode::End,
+            },
+
+            #[name(editor)]
+            gtk::Entry {
+                #[watch]
+                set_visible: self.is_renaming,
+                set_hexpand: true,
+
+                connect_activate[sender] => move |entry| {
+                    sender.input(ListRowInput::Rename(entry.buffer().text().to_string()));
+                },
+            },
+
+            gtk::Label {
+                #[watch]
+                set_visible: self.active_count > 0,
+                #[watch]
+                set_label: &self.active_count.to_string(),
+                add_css_class: "dim-label",
+            },
+        }
+    }
+
+    fn forward_to_parent(output: Self::Output) -> Option<AppMsg> {
+        Some(match output {
+            ListRowOutput::Renamed(id, name) => AppMsg::RenameList(id, name),
+        })
+    }
+
+    fn init_model((record, active_count): Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self {
+            id: record.id,
+            name: record.name,
+            active_count,
+            is_renaming: false,
+        }
+    }
+
+    fn update_with_view(&mut self, widgets: &mut Self::Widgets, message: Self::Input, sender: FactorySender<Self>) {
+        let start_rename = matches!(message, ListRowInput::StartRename);
+
+        match message {
+            ListRowInput::SetCount(count) => self.active_count = count,
+            ListRowInput::StartRename => {
+                self.is_renaming = true;
+                widgets.editor.set_text(&self.name);
+            }
+            ListRowInput::Rename(name) => {
+                self.is_renaming = false;
+                let name = name.trim();
+                if !name.is_empty() && name != self.name {
+                    self.name = name.to_string();
+                    sender.output(ListRowOutput::Renamed(self.id, self.name.clone()));
+                }
+            }
+        }
+
+        self.update_view(widgets, sender);
+        // Only once the entry is shown
+        if start_rename {
+            widgets.editor.grab_focus();
+        }
+    }
+}
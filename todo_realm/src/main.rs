@@ -1,14 +1,41 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::thread;
+
 use gtk::prelude::*;
 use relm4::factory::FactoryVecDeque;
-use relm4::gtk::Align;
+use relm4::gtk::{gdk, Align};
 use relm4::prelude::*;
+use todo_core::caldav::{self, Report, Tombstone};
+use todo_core::{Completable, Filter};
+
+mod lists;
+mod secrets;
+mod storage;
+mod subtask;
+
+use lists::{ListRow, ListRowInput};
+use storage::{Storage, TaskRecord};
+use subtask::Subtask;
 
-#[derive(Debug, Clone)]
 struct Task {
     idx: DynamicIndex,
+    id: i64,
     name: String,
     completed: bool,
     is_editing: bool,
+    is_expanded: bool,
+    subtasks: FactoryVecDeque<Subtask>,
+}
+
+impl Completable for Task {
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -16,17 +43,29 @@ enum TaskInput {
     Toggle(bool),
     Edit,
     Rename(String),
+    CancelEdit,
+    FocusLost,
+    Expand(bool),
+    AddSubtask(String),
+    SubtaskAdded(TaskRecord),
+    SubtaskToggled(i64, bool),
+    DeleteSubtask(DynamicIndex),
+    ClearCompleted,
 }
 
 #[derive(Debug, Clone)]
 enum TaskOutput {
-    Toggle,
+    Toggle(i64, bool),
+    Rename(i64, String),
     Delete(DynamicIndex),
+    AddSubtask(DynamicIndex, i64, String),
+    DeleteSubtask(i64),
 }
 
 #[relm4::factory]
 impl FactoryComponent for Task {
-    type Init = String;
+    /// A task with its subtasks
+    type Init = (TaskRecord, Vec<TaskRecord>);
     type Input = TaskInput;
     type Output = TaskOutput;
     type CommandOutput = ();
@@ -35,61 +74,130 @@ impl FactoryComponent for Task {
 
     view! {
         gtk::Box {
-            set_orientation: gtk::Orientation::Horizontal,
-
-            gtk::CheckButton {
-                set_active: false,
-                set_margin_all: 12,
-                connect_toggled[sender] => move |checkbox| {
-                    sender.input(TaskInput::Toggle(checkbox.is_active()));
-                    sender.output(TaskOutput::Toggle);
-                }
-            },
+            set_orientation: gtk::Orientation::Vertical,
 
-            #[name(label)]
-            gtk::Label {
-                set_visible: !self.is_editing,
-                #[watch]
-                set_label: &self.name,
-                set_hexpand: true,
-                set_halign: Align::Start,
-                set_margin_all: 6,
-            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+
+                gtk::ToggleButton {
+                    #[watch]
+                    set_icon_name: expander_icon(self.is_expanded),
+                    #[watch]
+                    #[block_signal(expand_handler)]
+                    set_active: self.is_expanded,
+                    add_css_class: "flat",
+                    set_valign: Align::Center,
+                    set_margin_start: 6,
+                    connect_toggled[sender] => move |btn| {
+                        sender.input(TaskInput::Expand(btn.is_active()));
+                    } @expand_handler
+                },
 
-            #[name(editor)]
-            gtk::Entry {
-                set_visible: self.is_editing,
-                #[watch]
-                set_text: &self.name,
-                set_hexpand: true,
-                set_hexpand_set: true,
-                set_halign: Align::Start,
-                set_margin_all: 6,
-
-                connect_activate[sender] => move |entry| {
-                    sender.input(TaskInput::Rename(entry.buffer().text().to_string()));
+                gtk::CheckButton {
+                    #[watch]
+                    #[block_signal(toggle_handler)]
+                    set_active: self.completed,
+                    set_margin_all: 12,
+                    connect_toggled[sender] => move |checkbox| {
+                        sender.input(TaskInput::Toggle(checkbox.is_active()));
+                    } @toggle_handler
+                },
+
+                gtk::Label {
+                    #[watch]
+                    set_visible: !self.is_editing,
+                    #[watch]
+                    set_label: &self.name,
+                    #[watch]
+                    set_attributes: Some(&strikethrough(self.completed)),
+                    set_hexpand: true,
+                    set_halign: Align::Start,
+                    set_margin_all: 6,
+                },
+
+                gtk::Label {
+                    #[watch]
+                    set_visible: !self.subtasks.is_empty(),
+                    #[watch]
+                    set_label: &self.progress(),
+                    add_css_class: "dim-label",
+                    set_margin_all: 6,
                 },
-            },
 
-            #[name(btn_edit)]
-            gtk::Button {
-                set_visible: !self.is_editing,
-                set_icon_name: "view-refresh",
-                set_margin_all: 6,
+                #[name(editor)]
+                gtk::Entry {
+                    #[watch]
+                    set_visible: self.is_editing,
+                    set_hexpand: true,
+                    set_hexpand_set: true,
+                    set_halign: Align::Start,
+                    set_margin_all: 6,
 
-                connect_clicked[sender] => move |_| {
-                    sender.input(TaskInput::Edit);
+                    connect_activate[sender] => move |entry| {
+                        sender.input(TaskInput::Rename(entry.buffer().text().to_string()));
+                    },
+
+                    add_controller = gtk::EventControllerKey {
+                        connect_key_pressed[sender] => move |_, key, _, _| {
+                            if key == gdk::Key::Escape {
+                                sender.input(TaskInput::CancelEdit);
+                                gtk::Inhibit(true)
+                            } else {
+                                gtk::Inhibit(false)
+                            }
+                        }
+                    },
+
+                    add_controller = gtk::EventControllerFocus {
+                        connect_leave[sender] => move |_| {
+                            sender.input(TaskInput::FocusLost);
+                        }
+                    },
+                },
+
+                gtk::Button {
+                    #[watch]
+                    set_visible: !self.is_editing,
+                    set_icon_name: "view-refresh",
+                    set_margin_all: 6,
+
+                    connect_clicked[sender] => move |_| {
+                        sender.input(TaskInput::Edit);
+                    }
+                },
+
+                // Always shown: leaving the editor saves the edit and ends it, so a button that
+                // only showed while editing would be gone before a click on it landed
+                gtk::Button {
+                    set_icon_name: "edit-delete",
+                    set_margin_all: 6,
+
+                    connect_clicked[sender, index] => move |_| {
+                        sender.output(TaskOutput::Delete(index.clone()));
+                    }
                 }
             },
 
-            #[name(btn_delete)]
-            gtk::Button {
-                set_visible: self.is_editing,
-                set_icon_name: "edit-delete",
-                set_margin_all: 6,
+            gtk::Revealer {
+                #[watch]
+                set_reveal_child: self.is_expanded,
 
-                connect_clicked[sender, index] => move |_| {
-                    sender.output(TaskOutput::Delete(index.clone()));
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+
+                    append: self.subtasks.widget(),
+
+                    gtk::Entry {
+                        set_placeholder_text: Some("Add a subtask"),
+                        set_margin_start: 30,
+                        set_margin_end: 6,
+                        set_margin_bottom: 6,
+                        connect_activate[sender] => move |entry| {
+                            let buffer = entry.buffer();
+                            sender.input(TaskInput::AddSubtask(buffer.text().into()));
+                            buffer.delete_text(0, None);
+                        }
+                    }
                 }
             }
         }
@@ -97,66 +205,194 @@ impl FactoryComponent for Task {
 
     fn forward_to_parent(output: Self::Output) -> Option<AppMsg> {
         Some(match output {
-            TaskOutput::Toggle => AppMsg::RefreshTaskCount,
+            TaskOutput::Toggle(id, completed) => AppMsg::SetCompleted(id, completed),
+            TaskOutput::Rename(id, name) => AppMsg::RenameEntry(id, name),
             TaskOutput::Delete(index) => AppMsg::DeleteEntry(index),
+            TaskOutput::AddSubtask(index, id, name) => AppMsg::AddSubtask(index, id, name),
+            TaskOutput::DeleteSubtask(id) => AppMsg::DeleteSubtask(id),
         })
     }
 
-    fn init_model(name: Self::Init, index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+    fn init_model((record, subtasks): Self::Init, index: &DynamicIndex, sender: FactorySender<Self>) -> Self {
+        let mut factory = FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender());
+        {
+            let mut guard = factory.guard();
+            for subtask in subtasks {
+                guard.push_back(subtask);
+            }
+        }
+
         Self {
             idx: index.clone(),
-            name,
-            completed: false,
+            id: record.id,
+            name: record.name,
+            completed: record.completed,
             is_editing: false,
+            is_expanded: false,
+            subtasks: factory,
         }
     }
 
-    fn update_with_view(&mut self, widgets: &mut Self::Widgets, message: Self::Input, _sender: FactorySender<Self>) {
+    fn update_with_view(&mut self, widgets: &mut Self::Widgets, message: Self::Input, sender: FactorySender<Self>) {
+        let start_editing = matches!(message, TaskInput::Edit);
+
         match message {
             TaskInput::Toggle(x) => {
-                self.completed = x;
-
-                let attrs = widgets.label.attributes().unwrap_or_default();
-                attrs.change(gtk::pango::AttrInt::new_strikethrough(self.completed));
-                widgets.label.set_attributes(Some(&attrs));
+                if x != self.completed {
+                    self.mark_completed(&sender, x);
+
+                    // Checking off a task checks off all of its subtasks, and the other way round
+                    let mut subtasks = self.subtasks.guard();
+                    for subtask in subtasks.iter_mut().filter(|subtask| subtask.completed != x) {
+                        subtask.completed = x;
+                        sender.output(TaskOutput::Toggle(subtask.id, x));
+                    }
+                }
             },
             TaskInput::Edit => {
                 self.is_editing = true;
-
-                widgets.label.set_visible(false);
-                widgets.editor.set_visible(true);
-                widgets.btn_edit.set_visible(false);
-                widgets.btn_delete.set_visible(true);
-                widgets.editor.grab_focus();
+                widgets.editor.set_text(&self.name);
+            },
+            TaskInput::Rename(name) => self.finish_edit(&sender, &name),
+            // Hiding the entry after Enter or Escape also takes the focus away, by then there's nothing to save
+            TaskInput::FocusLost => {
+                let name = widgets.editor.text();
+                self.finish_edit(&sender, &name);
+            },
+            // The entry is filled in again from `name` on the next edit
+            TaskInput::CancelEdit => self.is_editing = false,
+            TaskInput::Expand(expanded) => self.is_expanded = expanded,
+            TaskInput::AddSubtask(name) => {
+                let name = name.trim();
+                if !name.is_empty() {
+                    sender.output(TaskOutput::AddSubtask(self.idx.clone(), self.id, name.to_string()));
+                }
+            },
+            TaskInput::SubtaskAdded(record) => {
+                self.subtasks.guard().push_back(record);
+                self.sync_with_subtasks(&sender);
+            },
+            TaskInput::SubtaskToggled(id, completed) => {
+                sender.output(TaskOutput::Toggle(id, completed));
+                self.sync_with_subtasks(&sender);
+            },
+            TaskInput::DeleteSubtask(index) => {
+                let removed = self.subtasks.guard().remove(index.current_index());
+                if let Some(subtask) = removed {
+                    sender.output(TaskOutput::DeleteSubtask(subtask.id));
+                }
+                self.sync_with_subtasks(&sender);
+            },
+            TaskInput::ClearCompleted => {
+                let mut subtasks = self.subtasks.guard();
+                // Back to front, so the remaining indices stay valid
+                for i in (0..subtasks.len()).rev() {
+                    if subtasks.get(i).map_or(false, |subtask| subtask.completed) {
+                        subtasks.remove(i);
+                    }
+                }
             },
-            TaskInput::Rename(name) => {
-                self.is_editing = false;
-                self.name = name;
+        }
+
+        self.update_view(widgets, sender);
+        // Only once the entry is shown
+        if start_editing {
+            widgets.editor.grab_focus();
+        }
+    }
+}
 
-                widgets.label.set_text(&self.name);
+impl Task {
+    /// Completed and total subtasks, e.g. "2/5".
+    fn progress(&self) -> String {
+        let total = self.subtasks.len();
+        let done = total - todo_core::count_active(self.subtasks.iter());
+        format!("{done}/{total}")
+    }
 
-                widgets.editor.set_visible(false);
-                widgets.label.set_visible(true);
-                widgets.btn_delete.set_visible(false);
-                widgets.btn_edit.set_visible(true);
-            },
+    /// Saves the edited name. Saving an empty one deletes the task, as in TodoMVC.
+    fn finish_edit(&mut self, sender: &FactorySender<Self>, name: &str) {
+        if !self.is_editing {
+            return;
         }
+        self.is_editing = false;
+
+        let name = name.trim();
+        if name.is_empty() {
+            sender.output(TaskOutput::Delete(self.idx.clone()));
+        } else if name != self.name {
+            self.name = name.to_string();
+            sender.output(TaskOutput::Rename(self.id, self.name.clone()));
+        }
+    }
+
+    fn mark_completed(&mut self, sender: &FactorySender<Self>, completed: bool) {
+        self.completed = completed;
+        sender.output(TaskOutput::Toggle(self.id, completed));
     }
+
+    /// A task with subtasks is done exactly when all of them are.
+    fn sync_with_subtasks(&mut self, sender: &FactorySender<Self>) {
+        if self.subtasks.is_empty() {
+            return;
+        }
+        let all_done = todo_core::count_active(self.subtasks.iter()) == 0;
+        if all_done != self.completed {
+            self.mark_completed(sender, all_done);
+        }
+    }
+}
+
+fn expander_icon(expanded: bool) -> &'static str {
+    if expanded { "pan-down-symbolic" } else { "pan-end-symbolic" }
+}
+
+fn strikethrough(enabled: bool) -> gtk::pango::AttrList {
+    let attrs = gtk::pango::AttrList::new();
+    attrs.insert(gtk::pango::AttrInt::new_strikethrough(enabled));
+    attrs
 }
 
 #[derive(Debug, Clone)]
 enum AppMsg {
     DeleteEntry(DynamicIndex),
     AddEntry(String),
+    ToggleAll,
     ClearComplete,
-    RefreshTaskCount,
+    SetCompleted(i64, bool),
+    RenameEntry(i64, String),
     SetFilter(Filter),
+    AddSubtask(DynamicIndex, i64, String),
+    DeleteSubtask(i64),
+    SelectList(usize),
+    AddList(String),
+    RenameList(i64, String),
+    StartRenameList,
+    DeleteList,
+    MoveList(isize),
+    EditSyncUrl(String),
+    Sync,
+    /// The outcome of syncing a list, and the deletions that were sent
+    SyncDone(i64, Vec<Tombstone>, Result<Report, String>),
 }
 
 struct App {
+    storage: Storage,
+    lists: FactoryVecDeque<ListRow>,
+    /// The list whose tasks are shown
+    list_id: i64,
     tasks: FactoryVecDeque<Task>,
     task_count: usize,
     filter: Filter,
+    // Read by the list box filter function, indexed by row
+    visible_rows: Rc<RefCell<Vec<bool>>>,
+    /// The CalDAV collection URL in the sidebar, as typed
+    sync_url: String,
+    sync_status: String,
+    syncing: bool,
+    /// Set when the sync URL entry has to show `sync_url` again: another list is shown, or a
+    /// login was taken out of the typed URL
+    sync_url_replaced: bool,
 }
 
 #[relm4::component]
@@ -167,110 +403,384 @@ impl SimpleComponent for App {
 
     view! {
         main_window = gtk::ApplicationWindow {
-            set_width_request: 360,
-            set_title: Some("Todos"),
-
-            gtk::Box {
-                set_orientation: gtk::Orientation::Vertical,
-                set_margin_all: 12,
-                set_spacing: 6,
+            set_width_request: 560,
+            #[watch]
+            set_title: Some(&model.title()),
 
-                gtk::Entry {
-                    set_placeholder_text: Some("What needs to be done?"),
-                    connect_activate[sender] => move |entry| {
-                        let buffer = entry.buffer();
-                        sender.input(AppMsg::AddEntry(buffer.text().into()));
-                        buffer.delete_text(0, None);
-                    }
-                },
-
-                gtk::Box {
-                    set_orientation: gtk::Orientation::Horizontal,
-                    set_spacing: 12,
+            gtk::Paned {
+                #[watch]
+                set_sensitive: !model.syncing,
+                set_orientation: gtk::Orientation::Horizontal,
+                set_position: 180,
+                set_shrink_start_child: false,
+
+                #[wrap(Some)]
+                set_start_child = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_margin_all: 12,
+                    set_spacing: 6,
+
+                    gtk::ScrolledWindow {
+                        set_hscrollbar_policy: gtk::PolicyType::Never,
+                        set_vexpand: true,
+
+                        #[local_ref]
+                        sidebar_list_box -> gtk::ListBox {
+                            connect_row_selected[sender] => move |_, row| {
+                                if let Some(row) = row {
+                                    sender.input(AppMsg::SelectList(row.index() as usize));
+                                }
+                            }
+                        }
+                    },
 
-                    gtk::Label {
-                        #[watch]
-                        set_label: {
-                            let count = model.task_count;
-                            &format!("{} {}", count, if count == 1 { "task" } else { "tasks" })
-                        },
+                    gtk::Entry {
+                        set_placeholder_text: Some("New list"),
+                        connect_activate[sender] => move |entry| {
+                            let buffer = entry.buffer();
+                            sender.input(AppMsg::AddList(buffer.text().into()));
+                            buffer.delete_text(0, None);
+                        }
                     },
 
                     gtk::Box {
                         set_orientation: gtk::Orientation::Horizontal,
-                        set_hexpand: true,
                         set_halign: Align::Center,
                         set_spacing: 3,
 
-                        #[name(filter_btn)]
-                        gtk::ToggleButton {
-                            set_label: "All",
-                            set_active: true,
-                            connect_toggled[sender] => move |_| {
-                                sender.input(AppMsg::SetFilter(Filter::All));
+                        gtk::Button {
+                            set_icon_name: "document-edit-symbolic",
+                            set_tooltip_text: Some("Rename list"),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::StartRenameList);
+                            }
+                        },
+                        gtk::Button {
+                            set_icon_name: "go-up-symbolic",
+                            set_tooltip_text: Some("Move up"),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::MoveList(-1));
                             }
                         },
-                        gtk::ToggleButton {
-                            set_label: "Active",
-                            set_group: Some(&filter_btn),
-                            connect_toggled[sender] => move |_| {
-                                sender.input(AppMsg::SetFilter(Filter::Active));
+                        gtk::Button {
+                            set_icon_name: "go-down-symbolic",
+                            set_tooltip_text: Some("Move down"),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::MoveList(1));
                             }
                         },
-                        gtk::ToggleButton {
-                            set_label: "Complete",
-                            set_group: Some(&filter_btn),
-                            connect_toggled[sender] => move |_| {
-                                sender.input(AppMsg::SetFilter(Filter::Complete));
+                        gtk::Button {
+                            set_icon_name: "edit-delete-symbolic",
+                            set_tooltip_text: Some("Delete list and its tasks"),
+                            #[watch]
+                            set_sensitive: model.lists.len() > 1,
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::DeleteList);
                             }
                         }
                     },
 
-                    gtk::Button {
-                        set_label: "Clear Complete",
-                        connect_clicked[sender] => move |_| {
-                            sender.input(AppMsg::ClearComplete);
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 3,
+
+                        gtk::Entry {
+                            set_placeholder_text: Some("CalDAV collection URL"),
+                            set_hexpand: true,
+                            #[track(model.sync_url_replaced)]
+                            set_text: &model.sync_url,
+                            connect_changed[sender] => move |entry| {
+                                sender.input(AppMsg::EditSyncUrl(entry.text().into()));
+                            },
+                            connect_activate[sender] => move |_| {
+                                sender.input(AppMsg::Sync);
+                            }
+                        },
+                        gtk::Button {
+                            set_icon_name: "view-refresh-symbolic",
+                            set_tooltip_text: Some("Sync"),
+                            #[watch]
+                            set_sensitive: !model.sync_url.trim().is_empty(),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::Sync);
+                            }
                         }
+                    },
+
+                    gtk::Label {
+                        #[watch]
+                        set_visible: !model.sync_status.is_empty(),
+                        #[watch]
+                        set_label: &model.sync_status,
+                        set_wrap: true,
+                        set_xalign: 0.0,
+                        add_css_class: "dim-label",
                     }
                 },
 
-                gtk::ScrolledWindow {
-                    set_hscrollbar_policy: gtk::PolicyType::Never,
-                    set_min_content_height: 360,
-                    set_vexpand: true,
+                #[wrap(Some)]
+                set_end_child = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_margin_all: 12,
+                    set_spacing: 6,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 6,
+
+                        gtk::Button {
+                            set_icon_name: "object-select-symbolic",
+                            set_tooltip_text: Some("Mark all as complete"),
+                            #[watch]
+                            set_sensitive: !model.tasks.is_empty(),
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::ToggleAll);
+                            }
+                        },
+
+                        gtk::Entry {
+                            set_placeholder_text: Some("What needs to be done?"),
+                            set_hexpand: true,
+                            connect_activate[sender] => move |entry| {
+                                let buffer = entry.buffer();
+                                sender.input(AppMsg::AddEntry(buffer.text().into()));
+                                buffer.delete_text(0, None);
+                            }
+                        }
+                    },
 
-                    #[local_ref]
-                    task_list_box -> gtk::ListBox {}
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 12,
+
+                        gtk::Label {
+                            #[watch]
+                            set_label: &todo_core::tasks_left_label(model.task_count),
+                        },
+
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_hexpand: true,
+                            set_halign: Align::Center,
+                            set_spacing: 3,
+
+                            #[name(filter_btn)]
+                            gtk::ToggleButton {
+                                set_label: "All",
+                                set_active: true,
+                                connect_toggled[sender] => move |btn| {
+                                    // Buttons in a group also report being switched off
+                                    if btn.is_active() {
+                                        sender.input(AppMsg::SetFilter(Filter::All));
+                                    }
+                                }
+                            },
+                            gtk::ToggleButton {
+                                set_label: "Active",
+                                set_group: Some(&filter_btn),
+                                connect_toggled[sender] => move |btn| {
+                                    if btn.is_active() {
+                                        sender.input(AppMsg::SetFilter(Filter::Active));
+                                    }
+                                }
+                            },
+                            gtk::ToggleButton {
+                                set_label: "Complete",
+                                set_group: Some(&filter_btn),
+                                connect_toggled[sender] => move |btn| {
+                                    if btn.is_active() {
+                                        sender.input(AppMsg::SetFilter(Filter::Completed));
+                                    }
+                                }
+                            }
+                        },
+
+                        gtk::Button {
+                            set_label: "Clear Complete",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(AppMsg::ClearComplete);
+                            }
+                        }
+                    },
+
+                    gtk::ScrolledWindow {
+                        set_hscrollbar_policy: gtk::PolicyType::Never,
+                        set_min_content_height: 360,
+                        set_vexpand: true,
+
+                        #[local_ref]
+                        task_list_box -> gtk::ListBox {}
+                    }
                 }
             }
         }
     }
 
-    fn update(&mut self, msg: AppMsg, _sender: ComponentSender<Self>) {
+    fn update(&mut self, msg: AppMsg, sender: ComponentSender<Self>) {
+        self.sync_url_replaced = false;
+
         match msg {
             AppMsg::DeleteEntry(index) => {
-                self.tasks.guard().remove(index.current_index());
-                self.recount_tasks();
+                let removed = self.tasks.guard().remove(index.current_index());
+                if let Some(task) = removed {
+                    storage::log_error(self.storage.delete_task(task.id));
+                }
+                self.refresh_tasks();
             }
             AppMsg::AddEntry(name) => {
-                self.tasks.guard().push_back(name);
-                self.recount_tasks();
+                let name = name.trim();
+                if name.is_empty() {
+                    return;
+                }
+                if let Some(record) = storage::log_error(self.storage.insert_task(self.list_id, name, None)) {
+                    self.tasks.guard().push_back((record, Vec::new()));
+                }
+                self.refresh_tasks();
+            }
+            AppMsg::ToggleAll => {
+                // Each task saves itself and its subtasks, then reports back through `SetCompleted`
+                let check = self.task_count > 0;
+                for i in 0..self.tasks.len() {
+                    self.tasks.send(i, TaskInput::Toggle(check));
+                }
             }
             AppMsg::ClearComplete => {
                 let to_remove = self.tasks.iter()
-                    .filter_map(|todo| todo.completed.then_some(todo.idx.clone()))
+                    .filter_map(|todo| Filter::Completed.matches(todo).then_some(todo.idx.clone()))
                     .collect::<Vec<_>>();
                 for idx in to_remove {
                     self.tasks.guard().remove(idx.current_index());
                 }
+                for i in 0..self.tasks.len() {
+                    self.tasks.send(i, TaskInput::ClearCompleted);
+                }
+                storage::log_error(self.storage.delete_completed(self.list_id));
 
-                self.recount_tasks();
+                self.refresh_tasks();
+            }
+            AppMsg::SetCompleted(id, completed) => {
+                storage::log_error(self.storage.set_completed(id, completed));
+                self.refresh_tasks();
+            }
+            AppMsg::RenameEntry(id, name) => {
+                storage::log_error(self.storage.rename_task(id, &name));
+                self.refresh_tasks();
             }
             AppMsg::SetFilter(filter) => {
                 self.filter = filter;
+                self.refresh_tasks();
             }
-            AppMsg::RefreshTaskCount => {
-                self.recount_tasks();
+            AppMsg::AddSubtask(index, parent_id, name) => {
+                if let Some(record) = storage::log_error(self.storage.insert_task(self.list_id, &name, Some(parent_id))) {
+                    self.tasks.send(index.current_index(), TaskInput::SubtaskAdded(record));
+                }
+            }
+            AppMsg::DeleteSubtask(id) => {
+                storage::log_error(self.storage.delete_task(id));
+            }
+            AppMsg::SelectList(index) => {
+                let Some(list) = self.lists.get(index) else { return };
+                if list.id != self.list_id {
+                    self.list_id = list.id;
+                    self.load_tasks();
+                    self.refresh_tasks();
+                    self.show_sync_url();
+                }
+            }
+            AppMsg::AddList(name) => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return;
+                }
+                if let Some(record) = storage::log_error(self.storage.insert_list(name)) {
+                    self.lists.guard().push_back((record, 0));
+                    self.select_list_row(self.lists.len() - 1);
+                }
+            }
+            AppMsg::RenameList(id, name) => {
+                storage::log_error(self.storage.rename_list(id, &name));
+            }
+            AppMsg::StartRenameList => {
+                if let Some(index) = self.list_index() {
+                    self.lists.send(index, ListRowInput::StartRename);
+                }
+            }
+            AppMsg::DeleteList => {
+                // There's always a list to show tasks in
+                let Some(index) = self.list_index().filter(|_| self.lists.len() > 1) else { return };
+                if storage::log_error(self.storage.delete_list(self.list_id)).is_some() {
+                    self.lists.guard().remove(index);
+                    self.select_list_row(index.min(self.lists.len() - 1));
+                }
+            }
+            AppMsg::MoveList(offset) => {
+                let Some(index) = self.list_index() else { return };
+                let Some(target) = index.checked_add_signed(offset).filter(|target| *target < self.lists.len()) else {
+                    return;
+                };
+                self.lists.guard().move_to(index, target);
+                self.select_list_row(target);
+
+                let ids = self.lists.iter().map(|list| list.id).collect::<Vec<_>>();
+                storage::log_error(self.storage.reorder_lists(&ids));
+            }
+            AppMsg::EditSyncUrl(url) => self.sync_url = url,
+            AppMsg::Sync => {
+                if self.sync_url.trim().is_empty() || self.syncing {
+                    return;
+                }
+                let (url, typed_login) = match caldav::split_credentials(&self.sync_url) {
+                    Ok(split) => split,
+                    Err(e) => {
+                        self.sync_status = e.to_string();
+                        return;
+                    }
+                };
+                // A login typed into the URL goes to the keyring, the entry and the database only get the rest
+                let credentials = match typed_login {
+                    Some(credentials) => {
+                        secrets::log_error(secrets::store(&url, &credentials));
+                        self.sync_url = url.clone();
+                        self.sync_url_replaced = true;
+                        Some(credentials)
+                    }
+                    None => secrets::log_error(secrets::load(&url)).flatten(),
+                };
+                let client = match caldav::Client::new(&url, credentials.as_ref()) {
+                    Ok(client) => client,
+                    Err(e) => {
+                        self.sync_status = e.to_string();
+                        return;
+                    }
+                };
+                let saved = storage::log_error(self.storage.caldav_url(self.list_id)).flatten();
+                if saved.as_deref() != Some(url.as_str()) {
+                    storage::log_error(self.storage.set_caldav_url(self.list_id, Some(&url)));
+                }
+                let Some((tasks, tombstones)) = storage::log_error(self.storage.sync_snapshot(self.list_id)) else { return };
+
+                // The list stays as it is until the sync is done, so nothing can be edited meanwhile
+                self.syncing = true;
+                self.sync_status = "Syncing…".to_string();
+                let list_id = self.list_id;
+                thread::spawn(move || {
+                    let result = caldav::sync(&client, &tasks, &tombstones).map_err(|e| e.to_string());
+                    sender.input(AppMsg::SyncDone(list_id, tombstones, result));
+                });
+            }
+            AppMsg::SyncDone(list_id, tombstones, result) => {
+                self.syncing = false;
+                self.sync_status = match result {
+                    Ok(report) => match storage::log_error(self.storage.apply_sync(list_id, &report, &tombstones)) {
+                        Some(()) => sync_summary(&report),
+                        None => "Synced, but the changes couldn't be saved".to_string(),
+                    },
+                    Err(e) => format!("Sync failed: {e}"),
+                };
+                if list_id == self.list_id {
+                    self.load_tasks();
+                    self.refresh_tasks();
+                }
             }
         }
     }
@@ -280,26 +790,131 @@ impl SimpleComponent for App {
         root: &Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let model = App {
+        let storage = Storage::default_path()
+            .and_then(|path| storage::log_error(Storage::open(&path)))
+            .or_else(|| storage::log_error(Storage::open_in_memory()))
+            .expect("Failed to open task database");
+
+        let mut model = App {
+            lists: FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender()),
+            list_id: 0,
             tasks: FactoryVecDeque::new(gtk::ListBox::default(), sender.input_sender()),
+            storage,
             filter: Filter::All,
             task_count: 0,
+            visible_rows: Rc::default(),
+            sync_url: String::new(),
+            sync_status: String::new(),
+            syncing: false,
+            sync_url_replaced: false,
         };
 
+        let mut lists = storage::log_error(model.storage.load_lists()).unwrap_or_default();
+        if lists.is_empty() {
+            lists.extend(storage::log_error(model.storage.insert_list("Tasks")));
+        }
+        let counts = storage::log_error(model.storage.active_counts()).unwrap_or_default();
+        model.list_id = lists.first().map_or(0, |list| list.id);
+        {
+            let mut rows = model.lists.guard();
+            for list in lists {
+                let count = counts.get(&list.id).copied().unwrap_or(0);
+                rows.push_back((list, count));
+            }
+        }
+
+        model.load_tasks();
+        model.refresh_tasks();
+        model.show_sync_url();
+
+        let sidebar_list_box = model.lists.widget();
         let task_list_box = model.tasks.widget();
+        let visible_rows = model.visible_rows.clone();
+        task_list_box.set_filter_func(move |row| {
+            visible_rows.borrow().get(row.index() as usize).copied().unwrap_or(true)
+        });
 
         let widgets = view_output!();
+        model.select_list_row(0);
 
         ComponentParts { model, widgets }
     }
 }
 
-#[derive(Debug, Clone)]
-enum Filter { All, Active, Complete }
-
 impl App {
-    fn recount_tasks(&mut self) {
-        self.task_count = self.tasks.iter().filter(|todo| !todo.completed).count();
+    fn title(&self) -> String {
+        match self.lists.iter().find(|list| list.id == self.list_id) {
+            Some(list) => format!("Todos — {}", list.name),
+            None => "Todos".to_string(),
+        }
+    }
+
+    /// Position of the shown list in the sidebar.
+    fn list_index(&self) -> Option<usize> {
+        self.lists.iter().position(|list| list.id == self.list_id)
+    }
+
+    /// Selects a row in the sidebar, which shows its list once the selection is reported back.
+    fn select_list_row(&self, index: usize) {
+        let list_box = self.lists.widget();
+        list_box.select_row(list_box.row_at_index(index as i32).as_ref());
+    }
+
+    /// Fills in the sync URL of the shown list.
+    fn show_sync_url(&mut self) {
+        self.sync_url = storage::log_error(self.storage.caldav_url(self.list_id)).flatten().unwrap_or_default();
+        self.sync_status.clear();
+        self.sync_url_replaced = true;
+    }
+
+    /// Replaces the task rows with the tasks of the shown list.
+    fn load_tasks(&mut self) {
+        let mut tasks = self.tasks.guard();
+        tasks.clear();
+
+        let Some(records) = storage::log_error(self.storage.load_tasks(self.list_id)) else { return };
+        let (parents, children): (Vec<_>, Vec<_>) = records.into_iter()
+            .partition(|record| record.parent_id.is_none());
+
+        for record in parents {
+            let subtasks = children.iter()
+                .filter(|child| child.parent_id == Some(record.id))
+                .cloned()
+                .collect();
+            tasks.push_back((record, subtasks));
+        }
+    }
+
+    /// Updates the task count and which rows the current filter shows.
+    fn refresh_tasks(&mut self) {
+        self.task_count = todo_core::count_active(self.tasks.iter());
+        if let Some(index) = self.list_index() {
+            self.lists.send(index, ListRowInput::SetCount(self.task_count));
+        }
+
+        *self.visible_rows.borrow_mut() = self.tasks.iter()
+            .map(|task| self.filter.matches(task))
+            .collect();
+        self.tasks.widget().invalidate_filter();
+    }
+}
+
+/// What a sync did, e.g. "Synced: 2 sent, 1 received".
+fn sync_summary(report: &Report) -> String {
+    let counts = [
+        (report.uploaded, "sent"),
+        (report.downloaded, "received"),
+        (report.deleted, "deleted"),
+        (report.merged, "merged"),
+    ];
+    let done = counts.iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{count} {what}"))
+        .collect::<Vec<_>>();
+    if done.is_empty() {
+        "Up to date".to_string()
+    } else {
+        format!("Synced: {}", done.join(", "))
     }
 }
 
@@ -0,0 +1,29 @@
+use keyring::Entry;
+use todo_core::caldav::Credentials;
+
+const SERVICE: &str = "todo_realm CalDAV";
+
+/// The login for `collection_url`, one per collection. It's kept in the system keyring as
+/// `user:password`, like in basic auth, where the user can't contain a colon either.
+fn entry(collection_url: &str) -> keyring::Result<Entry> {
+    Entry::new(SERVICE, collection_url)
+}
+
+pub fn load(collection_url: &str) -> keyring::Result<Option<Credentials>> {
+    match entry(collection_url)?.get_password() {
+        Ok(secret) => Ok(secret.split_once(':').map(|(user, password)| Credentials {
+            user: user.to_string(),
+            password: password.to_string(),
+        })),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store(collection_url: &str, credentials: &Credentials) -> keyring::Result<()> {
+    entry(collection_url)?.set_password(&format!("{}:{}", credentials.user, credentials.password))
+}
+
+pub fn log_error<T>(result: keyring::Result<T>) -> Option<T> {
+    result.map_err(|e| eprintln!("keyring error: {e}")).ok()
+}
@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::{ffi, params, Connection, OptionalExtension};
+use todo_core::caldav::{LocalTask, Remote, Report, Tombstone};
+use todo_core::ical;
+
+/// Schema migrations, applied in order. The index of the last applied one is kept in
+/// `PRAGMA user_version`, so new columns go in a new entry rather than an edit to an old one.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE tasks (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        name       TEXT    NOT NULL,
+        completed  INTEGER NOT NULL DEFAULT 0,
+        position   INTEGER NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+        updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    CREATE INDEX tasks_position ON tasks (position);",
+    "ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks (id);
+    CREATE INDEX tasks_parent ON tasks (parent_id);",
+    // Existing tasks end up in a first list
+    "CREATE TABLE lists (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        name       TEXT    NOT NULL,
+        position   INTEGER NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+    );
+    INSERT INTO lists (id, name, position) VALUES (1, 'Tasks', 0);
+    ALTER TABLE tasks ADD COLUMN list_id INTEGER NOT NULL DEFAULT 1 REFERENCES lists (id);
+    CREATE INDEX tasks_list ON tasks (list_id);",
+    // CalDAV sync. `synced` is the task as of the last sync, as an iCalendar file
+    "ALTER TABLE lists ADD COLUMN caldav_url TEXT;
+    ALTER TABLE tasks ADD COLUMN uid TEXT;
+    UPDATE tasks SET uid = lower(hex(randomblob(16)));
+    CREATE UNIQUE INDEX tasks_uid ON tasks (list_id, uid);
+    ALTER TABLE tasks ADD COLUMN href TEXT;
+    ALTER TABLE tasks ADD COLUMN etag TEXT;
+    ALTER TABLE tasks ADD COLUMN synced TEXT;
+    ALTER TABLE tasks ADD COLUMN dirty INTEGER NOT NULL DEFAULT 1;
+    CREATE TABLE deleted_tasks (
+        list_id INTEGER NOT NULL REFERENCES lists (id),
+        uid     TEXT    NOT NULL,
+        href    TEXT    NOT NULL,
+        etag    TEXT
+    );",
+];
+
+/// Synced tasks that are about to be deleted, so the deletion reaches the server too.
+const RECORD_DELETIONS: &str = "INSERT INTO deleted_tasks (list_id, uid, href, etag)
+    SELECT list_id, uid, href, etag FROM tasks WHERE href IS NOT NULL AND";
+
+#[derive(Debug, Clone)]
+pub struct TaskRecord {
+    pub id: i64,
+    pub name: String,
+    pub completed: bool,
+    /// The task this one is a subtask of. Subtasks only go one level deep.
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListRecord {
+    pub id: i64,
+    pub name: String,
+}
+
+pub struct Storage {
+    conn: Connection,
+}
+
+impl Storage {
+    /// Default database location, inside the user's data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("todo_realm").join("tasks.sqlite3"))
+    }
+
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        if let Some(dir) = path.parent() {
+            // If this fails, opening the database reports a better error anyway
+            let _ = fs::create_dir_all(dir);
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> rusqlite::Result<Self> {
+        migrate(&mut conn)?;
+        Ok(Self { conn })
+    }
+
+    pub fn load_lists(&self) -> rusqlite::Result<Vec<ListRecord>> {
+        let mut stmt = self.conn.prepare("SELECT id, name FROM lists ORDER BY position, id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ListRecord {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Appends a list after the others.
+    pub fn insert_list(&self, name: &str) -> rusqlite::Result<ListRecord> {
+        let position: Option<i64> = self.conn
+            .query_row("SELECT MAX(position) FROM lists", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+
+        self.conn.execute(
+            "INSERT INTO lists (name, position) VALUES (?1, ?2)",
+            params![name, position.map_or(0, |p| p + 1)],
+        )?;
+
+        Ok(ListRecord {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+        })
+    }
+
+    pub fn rename_list(&self, id: i64, name: &str) -> rusqlite::Result<()> {
+        self.conn.execute("UPDATE lists SET name = ?2 WHERE id = ?1", params![id, name])?;
+        Ok(())
+    }
+
+    /// Deletes a list along with all of its tasks. Its CalDAV collection is left alone.
+    pub fn delete_list(&self, id: i64) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM deleted_tasks WHERE list_id = ?1", params![id])?;
+        tx.execute("DELETE FROM tasks WHERE list_id = ?1", params![id])?;
+        tx.execute("DELETE FROM lists WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    /// Stores the order of the lists, given all their ids from first to last.
+    pub fn reorder_lists(&self, ids: &[i64]) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute("UPDATE lists SET position = ?2 WHERE id = ?1", params![id, position])?;
+        }
+        tx.commit()
+    }
+
+    /// Number of unfinished top-level tasks in each list. Lists with none are left out.
+    pub fn active_counts(&self) -> rusqlite::Result<HashMap<i64, usize>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT list_id, COUNT(*) FROM tasks
+            WHERE NOT completed AND parent_id IS NULL
+            GROUP BY list_id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Every task and subtask in a list, in order.
+    pub fn load_tasks(&self, list_id: i64) -> rusqlite::Result<Vec<TaskRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, completed, parent_id FROM tasks WHERE list_id = ?1 ORDER BY position, id",
+        )?;
+        let rows = stmt.query_map(params![list_id], |row| {
+            Ok(TaskRecord {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                completed: row.get(2)?,
+                parent_id: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Appends a task to the end of a list, or of `parent_id`'s subtasks.
+    pub fn insert_task(&self, list_id: i64, name: &str, parent_id: Option<i64>) -> rusqlite::Result<TaskRecord> {
+        let position: Option<i64> = self.conn
+            .query_row("SELECT MAX(position) FROM tasks", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+
+        self.conn.execute(
+            "INSERT INTO tasks (name, position, parent_id, list_id, uid)
+            VALUES (?1, ?2, ?3, ?4, lower(hex(randomblob(16))))",
+            params![name, position.map_or(0, |p| p + 1), parent_id, list_id],
+        )?;
+
+        Ok(TaskRecord {
+            id: self.conn.last_insert_rowid(),
+            name: name.to_string(),
+            completed: false,
+            parent_id,
+        })
+    }
+
+    pub fn rename_task(&self, id: i64, name: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE tasks SET name = ?2, dirty = 1, updated_at = strftime('%s', 'now') WHERE id = ?1",
+            params![id, name],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_completed(&self, id: i64, completed: bool) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE tasks SET completed = ?2, dirty = 1, updated_at = strftime('%s', 'now') WHERE id = ?1",
+            params![id, completed],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes a task along with its subtasks.
+    pub fn delete_task(&self, id: i64) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(&format!("{RECORD_DELETIONS} (id = ?1 OR parent_id = ?1)"), params![id])?;
+        tx.execute("DELETE FROM tasks WHERE id = ?1 OR parent_id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    /// Deletes a list's completed tasks and subtasks, and the subtasks of completed tasks.
+    pub fn delete_completed(&self, list_id: i64) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let completed = "list_id = ?1 AND (completed OR parent_id IN (SELECT id FROM tasks WHERE completed))";
+        tx.execute(&format!("{RECORD_DELETIONS} {completed}"), params![list_id])?;
+        tx.execute(&format!("DELETE FROM tasks WHERE {completed}"), params![list_id])?;
+        tx.commit()
+    }
+
+    /// The CalDAV collection a list is synced with. Its login is in the keyring, not here.
+    pub fn caldav_url(&self, list_id: i64) -> rusqlite::Result<Option<String>> {
+        self.conn.query_row("SELECT caldav_url FROM lists WHERE id = ?1", params![list_id], |row| row.get(0))
+    }
+
+    /// Points a list at another CalDAV collection, or none. Its tasks count as never synced
+    /// from then on.
+    pub fn set_caldav_url(&self, list_id: i64, url: Option<&str>) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE lists SET caldav_url = ?2 WHERE id = ?1", params![list_id, url])?;
+        tx.execute(
+            "UPDATE tasks SET href = NULL, etag = NULL, synced = NULL, dirty = 1 WHERE list_id = ?1",
+            params![list_id],
+        )?;
+        tx.execute("DELETE FROM deleted_tasks WHERE list_id = ?1", params![list_id])?;
+        tx.commit()
+    }
+
+    /// A list's tasks as todos, and its synced tasks deleted since the last sync.
+    pub fn sync_snapshot(&self, list_id: i64) -> rusqlite::Result<(Vec<LocalTask>, Vec<Tombstone>)> {
+        let mut stmt = self.conn.prepare(
+            "SELECT task.uid, task.name, task.completed, parent.uid, task.href, task.etag, task.synced, task.dirty
+            FROM tasks task LEFT JOIN tasks parent ON parent.id = task.parent_id
+            WHERE task.list_id = ?1 ORDER BY task.position, task.id",
+        )?;
+        let tasks = stmt.query_map(params![list_id], |row| {
+            let (uid, completed): (String, bool) = (row.get(0)?, row.get(2)?);
+            let base = row.get::<_, Option<String>>(6)?.and_then(|text| ical::parse(&text).into_iter().next());
+
+            // Whatever else the todo had on the server is sent back unchanged
+            let mut todo = base.clone().unwrap_or_default();
+            todo.uid = uid;
+            todo.summary = row.get(1)?;
+            if completed != todo.completed {
+                todo.completed = completed;
+                todo.completed_at = None;
+            }
+            todo.parent = row.get(3)?;
+            let etag = row.get(5)?;
+
+            Ok(LocalTask {
+                todo,
+                remote: row.get::<_, Option<String>>(4)?.map(|href| Remote { href, etag }),
+                base,
+                dirty: row.get(7)?,
+            })
+        })?.collect::<rusqlite::Result<_>>()?;
+
+        let mut stmt = self.conn.prepare("SELECT uid, href, etag FROM deleted_tasks WHERE list_id = ?1")?;
+        let tombstones = stmt.query_map(params![list_id], |row| {
+            Ok(Tombstone {
+                uid: row.get(0)?,
+                href: row.get(1)?,
+                etag: row.get(2)?,
+            })
+        })?.collect::<rusqlite::Result<_>>()?;
+
+        Ok((tasks, tombstones))
+    }
+
+    /// Stores the outcome of syncing a list with the `tombstones` from its snapshot.
+    pub fn apply_sync(&self, list_id: i64, report: &Report, tombstones: &[Tombstone]) -> rusqlite::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let stamp = Utc::now().naive_utc();
+
+        for (todo, remote) in &report.synced {
+            let synced = ical::write([todo], stamp);
+            let updated = tx.execute(
+                "UPDATE tasks SET name = ?3, completed = ?4, href = ?5, etag = ?6, synced = ?7, dirty = 0,
+                    updated_at = strftime('%s', 'now')
+                WHERE list_id = ?1 AND uid = ?2",
+                params![list_id, todo.uid, todo.summary, todo.completed, remote.href, remote.etag, synced],
+            )?;
+            if updated == 0 {
+                tx.execute(
+                    "INSERT INTO tasks (list_id, uid, name, completed, href, etag, synced, dirty, position)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, (SELECT IFNULL(MAX(position) + 1, 0) FROM tasks))",
+                    params![list_id, todo.uid, todo.summary, todo.completed, remote.href, remote.etag, synced],
+                )?;
+            }
+        }
+        // Once every task is there. Subtasks only go one level deep, so deeper ones are
+        // moved up under the top-level task
+        for (todo, _) in &report.synced {
+            tx.execute(
+                "UPDATE tasks SET parent_id = (
+                    SELECT IFNULL(parent.parent_id, parent.id) FROM tasks parent
+                    WHERE parent.list_id = ?1 AND parent.uid = ?3
+                ) WHERE list_id = ?1 AND uid = ?2",
+                params![list_id, todo.uid, todo.parent],
+            )?;
+        }
+
+        for uid in &report.removed {
+            // Its subtasks may not have been deleted on the server, they stay as top-level tasks
+            tx.execute(
+                "UPDATE tasks SET parent_id = NULL
+                WHERE parent_id IN (SELECT id FROM tasks WHERE list_id = ?1 AND uid = ?2)",
+                params![list_id, uid],
+            )?;
+            tx.execute("DELETE FROM tasks WHERE list_id = ?1 AND uid = ?2", params![list_id, uid])?;
+        }
+        for tombstone in tombstones {
+            tx.execute(
+                "DELETE FROM deleted_tasks WHERE list_id = ?1 AND uid = ?2",
+                params![list_id, tombstone.uid],
+            )?;
+        }
+        tx.commit()
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_MISMATCH),
+            Some(format!("database schema v{version} is newer than this app supports")),
+        ));
+    }
+
+    // Adding a column that references another table and has a default, as `list_id` does,
+    // fails on a table with rows while foreign keys are checked. The pragma has no effect
+    // inside a transaction, so it's switched off around all of them.
+    let foreign_keys: bool = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let migrated = apply_migrations(conn, version);
+    conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+    migrated
+}
+
+fn apply_migrations(conn: &mut Connection, version: usize) -> rusqlite::Result<()> {
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Database errors are reported, not fatal: the list on screen stays usable either way.
+pub fn log_error<T>(result: rusqlite::Result<T>) -> Option<T> {
+    result.map_err(|e| eprintln!("database error: {e}")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use todo_core::caldav::{Remote, Report};
+    use todo_core::ical::VTodo;
+
+    use super::*;
+
+    fn names(storage: &Storage, list_id: i64) -> Vec<String> {
+        storage.load_tasks(list_id).unwrap().into_iter().map(|task| task.name).collect()
+    }
+
+    fn schema_version(storage: &Storage) -> usize {
+        storage.conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    /// Marks every task of the list as synced, as a sync that uploaded them would.
+    fn sync_all(storage: &Storage, list_id: i64) {
+        let (tasks, tombstones) = storage.sync_snapshot(list_id).unwrap();
+        let synced = tasks.into_iter()
+            .map(|task| {
+                let remote = Remote { href: format!("/tasks/{}.ics", task.todo.uid), etag: Some("\"1\"".to_string()) };
+                (task.todo, remote)
+            })
+            .collect();
+        storage.apply_sync(list_id, &Report { synced, ..Report::default() }, &tombstones).unwrap();
+    }
+
+    #[test]
+    fn migrates_a_new_database() {
+        let storage = Storage::open_in_memory().unwrap();
+        assert_eq!(schema_version(&storage), MIGRATIONS.len());
+
+        let lists = storage.load_lists().unwrap();
+        assert_eq!(lists.len(), 1);
+        assert_eq!(lists[0].name, "Tasks");
+        assert!(storage.load_tasks(lists[0].id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrates_tasks_from_an_older_schema() {
+        // A database from before lists and sync, with a task and a subtask
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute_batch(MIGRATIONS[1]).unwrap();
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tasks (id, name, completed, position) VALUES (1, 'Buy milk', 1, 0);
+            INSERT INTO tasks (id, name, position, parent_id) VALUES (2, 'Oat milk', 1, 1);",
+        ).unwrap();
+
+        let storage = Storage::with_connection(conn).unwrap();
+        assert_eq!(schema_version(&storage), MIGRATIONS.len());
+        let list_id = storage.load_lists().unwrap()[0].id;
+        let tasks = storage.load_tasks(list_id).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[0].completed);
+        assert_eq!(tasks[1].parent_id, Some(1));
+
+        // Every task got its own UID and counts as never synced
+        let (tasks, tombstones) = storage.sync_snapshot(list_id).unwrap();
+        assert_ne!(tasks[0].todo.uid, tasks[1].todo.uid);
+        assert_eq!(tasks[1].todo.parent.as_ref(), Some(&tasks[0].todo.uid));
+        assert!(tasks.iter().all(|task| task.dirty && task.remote.is_none()));
+        assert!(tombstones.is_empty());
+    }
+
+    #[test]
+    fn refuses_a_newer_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        assert!(Storage::with_connection(conn).is_err());
+    }
+
+    #[test]
+    fn delete_completed_takes_subtasks_along() {
+        let storage = Storage::open_in_memory().unwrap();
+        let list_id = storage.load_lists().unwrap()[0].id;
+        let done = storage.insert_task(list_id, "Done", None).unwrap();
+        storage.insert_task(list_id, "Under done", Some(done.id)).unwrap();
+        let open = storage.insert_task(list_id, "Open", None).unwrap();
+        let done_subtask = storage.insert_task(list_id, "Done subtask", Some(open.id)).unwrap();
+        storage.insert_task(list_id, "Open subtask", Some(open.id)).unwrap();
+        storage.set_completed(done.id, true).unwrap();
+        storage.set_completed(done_subtask.id, true).unwrap();
+
+        let other_list = storage.insert_list("Other").unwrap();
+        let elsewhere = storage.insert_task(other_list.id, "Done elsewhere", None).unwrap();
+        storage.set_completed(elsewhere.id, true).unwrap();
+
+        storage.delete_completed(list_id).unwrap();
+        assert_eq!(names(&storage, list_id), ["Open", "Open subtask"]);
+        assert_eq!(names(&storage, other_list.id), ["Done elsewhere"]);
+    }
+
+    #[test]
+    fn deleting_synced_tasks_leaves_tombstones() {
+        let storage = Storage::open_in_memory().unwrap();
+        let list_id = storage.load_lists().unwrap()[0].id;
+        let parent = storage.insert_task(list_id, "Parent", None).unwrap();
+        storage.insert_task(list_id, "Child", Some(parent.id)).unwrap();
+        sync_all(&storage, list_id);
+        let unsynced = storage.insert_task(list_id, "Never synced", None).unwrap();
+
+        storage.delete_task(unsynced.id).unwrap();
+        assert!(storage.sync_snapshot(list_id).unwrap().1.is_empty());
+
+        storage.delete_task(parent.id).unwrap();
+        let (tasks, tombstones) = storage.sync_snapshot(list_id).unwrap();
+        assert!(tasks.is_empty());
+        assert_eq!(tombstones.len(), 2);
+        assert!(tombstones.iter().all(|tombstone| tombstone.etag.as_deref() == Some("\"1\"")));
+
+        // Once the sync has sent them they're gone
+        storage.apply_sync(list_id, &Report::default(), &tombstones).unwrap();
+        assert!(storage.sync_snapshot(list_id).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn edits_after_a_sync_are_dirty() {
+        let storage = Storage::open_in_memory().unwrap();
+        let list_id = storage.load_lists().unwrap()[0].id;
+        let task = storage.insert_task(list_id, "Buy milk", None).unwrap();
+        sync_all(&storage, list_id);
+        assert!(storage.sync_snapshot(list_id).unwrap().0.iter().all(|task| !task.dirty));
+
+        storage.rename_task(task.id, "Buy oat milk").unwrap();
+        let (tasks, _) = storage.sync_snapshot(list_id).unwrap();
+        assert!(tasks[0].dirty);
+        assert_eq!(tasks[0].todo.summary, "Buy oat milk");
+        assert_eq!(tasks[0].base.as_ref().map(|base| base.summary.as_str()), Some("Buy milk"));
+    }
+
+    #[test]
+    fn apply_sync_adds_updates_and_removes_tasks() {
+        let storage = Storage::open_in_memory().unwrap();
+        let list_id = storage.load_lists().unwrap()[0].id;
+        storage.insert_task(list_id, "Buy milk", None).unwrap();
+        storage.insert_task(list_id, "Call mum", None).unwrap();
+        sync_all(&storage, list_id);
+        let (tasks, _) = storage.sync_snapshot(list_id).unwrap();
+
+        let mut renamed = tasks[0].todo.clone();
+        renamed.summary = "Buy oat milk".to_string();
+        let mut added = VTodo::new("from-server", "Water plants");
+        added.parent = Some(renamed.uid.clone());
+        let remote = |uid: &str| Remote { href: format!("/tasks/{uid}.ics"), etag: Some("\"2\"".to_string()) };
+        let report = Report {
+            synced: vec![(renamed.clone(), remote(&renamed.uid)), (added, remote("from-server"))],
+            removed: vec![tasks[1].todo.uid.clone()],
+            ..Report::default()
+        };
+        storage.apply_sync(list_id, &report, &[]).unwrap();
+
+        let records = storage.load_tasks(list_id).unwrap();
+        assert_eq!(names(&storage, list_id), ["Buy oat milk", "Water plants"]);
+        assert_eq!(records[1].parent_id, Some(records[0].id));
+    }
+
+    #[test]
+    fn another_collection_starts_the_sync_over() {
+        let storage = Storage::open_in_memory().unwrap();
+        let list_id = storage.load_lists().unwrap()[0].id;
+        let task = storage.insert_task(list_id, "Buy milk", None).unwrap();
+        storage.insert_task(list_id, "Call mum", None).unwrap();
+        sync_all(&storage, list_id);
+        storage.delete_task(task.id).unwrap();
+
+        storage.set_caldav_url(list_id, Some("http://localhost:5232/me/tasks/")).unwrap();
+        assert_eq!(storage.caldav_url(list_id).unwrap().as_deref(), Some("http://localhost:5232/me/tasks/"));
+        let (tasks, tombstones) = storage.sync_snapshot(list_id).unwrap();
+        assert!(tasks.iter().all(|task| task.dirty && task.remote.is_none() && task.base.is_none()));
+        assert!(tombstones.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use gtk::prelude::*;
+use relm4::gtk::Align;
+use relm4::prelude::*;
+use todo_core::Completable;
+
+use crate::storage::TaskRecord;
+use crate::{strikethrough, TaskInput};
+
+#[derive(Debug)]
+pub(crate) struct Subtask {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) completed: bool,
+}
+
+impl Completable for Subtask {
+    fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum SubtaskInput {
+    Toggle(bool),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum SubtaskOutput {
+    Toggle(i64, bool),
+    Delete(DynamicIndex),
+}
+
+#[relm4::factory(pub(crate))]
+impl FactoryComponent for Subtask {
+    type Init = TaskRecord;
+    type Input = SubtaskInput;
+    type Output = SubtaskOutput;
+    type CommandOutput = ();
+    type ParentInput = TaskInput;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Horizontal,
+            set_margin_start: 24,
+
+            gtk::CheckButton {
+                #[watch]
+                #[block_signal(toggle_handler)]
+                set_active: self.completed,
+                set_margin_all: 6,
+                connect_toggled[sender] => move |checkbox| {
+                    sender.input(SubtaskInput::Toggle(checkbox.is_active()));
+                } @toggle_handler
+            },
+
+            gtk::Label {
+                set_label: &self.name,
+                #[watch]
+                set_attributes: Some(&strikethrough(self.completed)),
+                set_hexpand: true,
+                set_halign: Align::Start,
+                set_margin_all: 6,
+            },
+
+            gtk::Button {
+                set_icon_name: "edit-delete",
+                add_css_class: "flat",
+                set_margin_all: 3,
+
+                connect_clicked[sender, index] => move |_| {
+                    sender.output(SubtaskOutput::Delete(index.clone()));
+                }
+            }
+        }
+    }
+
+    fn forward_to_parent(output: Self::Output) -> Option<TaskInput> {
+        Some(match output {
+            SubtaskOutput::Toggle(id, completed) => TaskInput::SubtaskToggled(id, completed),
+            SubtaskOutput::Delete(index) => TaskInput::DeleteSubtask(index),
+        })
+    }
+
+    fn init_model(record: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self {
+            id: record.id,
+            name: record.name,
+            completed: record.completed,
+        }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
+        match message {
+            SubtaskInput::Toggle(completed) => {
+                if completed != self.completed {
+                    self.completed = completed;
+                    sender.output(SubtaskOutput::Toggle(self.id, completed));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use todo_core::{Filter, TaskList};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    /// Typing into the "What needs to be done?" line
+    Insert,
+    /// Editing the task at `index` in the list
+    Edit { index: usize, buffer: String },
+}
+
+#[derive(Debug)]
+pub struct App {
+    pub tasks: TaskList,
+    pub filter: Filter,
+    pub input: String,
+    pub mode: Mode,
+    /// Row in the filtered list, not a position in `tasks`
+    pub selected: usize,
+    pub should_quit: bool,
+    /// Set after the first `d` of `dd`
+    pending_delete: bool,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            tasks: TaskList::new(),
+            filter: Filter::All,
+            input: String::new(),
+            mode: Mode::Insert,
+            selected: 0,
+            should_quit: false,
+            pending_delete: false,
+        }
+    }
+
+    /// Positions in `tasks` of the rows the current filter shows.
+    pub fn visible(&self) -> Vec<usize> {
+        self.tasks.filtered(self.filter).map(|(index, _)| index).collect()
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.visible().get(self.selected).copied()
+    }
+
+    /// Keeps the selection on a row that exists after the list or filter changed.
+    fn clamp_selection(&mut self) {
+        let rows = self.visible().len();
+        self.selected = self.selected.min(rows.saturating_sub(1));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.should_quit = true;
+            return;
+        }
+
+        match self.mode {
+            Mode::Normal => self.normal_key(key),
+            Mode::Insert => self.insert_key(key),
+            Mode::Edit { .. } => self.edit_key(key),
+        }
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) {
+        let pending_delete = std::mem::take(&mut self.pending_delete);
+        let rows = self.visible().len();
+
+        match key.code {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected = (self.selected + 1).min(rows.saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => self.selected = 0,
+            KeyCode::Char('G') | KeyCode::End => self.selected = rows.saturating_sub(1),
+            KeyCode::Char('i' | 'o' | 'a') => self.mode = Mode::Insert,
+            KeyCode::Char(' ' | 'x') => {
+                if let Some(task) = self.selected_index().and_then(|index| self.tasks.get_mut(index)) {
+                    task.completed = !task.completed;
+                }
+                self.clamp_selection();
+            }
+            KeyCode::Char('e' | 'c') | KeyCode::Enter => {
+                if let Some(index) = self.selected_index() {
+                    let buffer = self.tasks.get(index).map(|task| task.description.clone()).unwrap_or_default();
+                    self.mode = Mode::Edit { index, buffer };
+                }
+            }
+            KeyCode::Char('d') if pending_delete => self.delete_selected(),
+            KeyCode::Char('d') => self.pending_delete = true,
+            KeyCode::Delete => self.delete_selected(),
+            KeyCode::Char('A') => {
+                self.tasks.toggle_all();
+                self.clamp_selection();
+            }
+            KeyCode::Char('C') => {
+                self.tasks.clear_completed();
+                self.clamp_selection();
+            }
+            KeyCode::Char('1') => self.set_filter(Filter::All),
+            KeyCode::Char('2') => self.set_filter(Filter::Active),
+            KeyCode::Char('3') => self.set_filter(Filter::Completed),
+            KeyCode::Char('f') | KeyCode::Tab => {
+                let next = Filter::ALL.iter().position(|filter| *filter == self.filter).map_or(0, |i| i + 1);
+                self.set_filter(Filter::ALL[next % Filter::ALL.len()]);
+            }
+            _ => {}
+        }
+    }
+
+    fn insert_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            // Like TodoMVC, the input stays focused for the next todo
+            KeyCode::Enter if self.tasks.add(&self.input).is_some() => self.input.clear(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+    }
+
+    fn edit_key(&mut self, key: KeyEvent) {
+        let Mode::Edit { index, buffer } = &mut self.mode else { return };
+        let index = *index;
+
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => {
+                let description = buffer.trim().to_string();
+                self.mode = Mode::Normal;
+                // Saving an empty todo deletes it, as in TodoMVC
+                if description.is_empty() {
+                    self.tasks.remove(index);
+                } else if let Some(task) = self.tasks.get_mut(index) {
+                    task.description = description;
+                }
+                self.clamp_selection();
+            }
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            KeyCode::Char(c) => buffer.push(c),
+            _ => {}
+        }
+    }
+
+    fn delete_selected(&mut self) {
+        if let Some(index) = self.selected_index() {
+            self.tasks.remove(index);
+        }
+        self.clamp_selection();
+    }
+
+    fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.clamp_selection();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    fn enter(app: &mut App) {
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    }
+
+    /// An app in normal mode with these tasks.
+    fn app_with(descriptions: &[&str]) -> App {
+        let mut app = App::new();
+        for description in descriptions {
+            press(&mut app, description);
+            enter(&mut app);
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        app
+    }
+
+    fn descriptions(app: &App) -> Vec<&str> {
+        app.tasks.iter().map(|task| task.description.as_str()).collect()
+    }
+
+    #[test]
+    fn enter_adds_only_non_empty_tasks() {
+        let mut app = App::new();
+        press(&mut app, "  ");
+        enter(&mut app);
+        assert!(app.tasks.is_empty());
+        assert_eq!(app.input, "  ");
+
+        press(&mut app, "milk");
+        enter(&mut app);
+        assert_eq!(descriptions(&app), ["milk"]);
+        assert!(app.input.is_empty());
+        assert_eq!(app.mode, Mode::Insert);
+    }
+
+    #[test]
+    fn dd_deletes_the_selected_task() {
+        let mut app = app_with(&["a", "b", "c"]);
+        press(&mut app, "jd");
+        assert_eq!(app.tasks.len(), 3);
+        press(&mut app, "d");
+        assert_eq!(descriptions(&app), ["a", "c"]);
+
+        // Another key in between cancels the first `d`
+        press(&mut app, "djd");
+        assert_eq!(app.tasks.len(), 2);
+    }
+
+    #[test]
+    fn toggle_all_and_clear_completed_keep_the_selection_in_range() {
+        let mut app = app_with(&["a", "b", "c"]);
+        press(&mut app, "2G");
+        assert_eq!(app.selected, 2);
+
+        press(&mut app, "A");
+        assert!(app.visible().is_empty());
+        assert_eq!(app.selected, 0);
+
+        press(&mut app, "1G");
+        assert_eq!(app.selected, 2);
+        press(&mut app, "C");
+        assert!(app.tasks.is_empty());
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn saving_an_empty_edit_removes_the_task() {
+        let mut app = app_with(&["a", "b"]);
+        press(&mut app, "je");
+        assert_eq!(app.mode, Mode::Edit { index: 1, buffer: "b".to_string() });
+
+        app.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        press(&mut app, " ");
+        enter(&mut app);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(descriptions(&app), ["a"]);
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn filters_cycle_and_apply() {
+        let mut app = app_with(&["a", "b"]);
+        press(&mut app, "x");
+        assert_eq!(app.visible(), [0, 1]);
+
+        press(&mut app, "f");
+        assert_eq!((app.filter, app.visible()), (Filter::Active, vec![1]));
+        press(&mut app, "f");
+        assert_eq!((app.filter, app.visible()), (Filter::Completed, vec![0]));
+        press(&mut app, "f");
+        assert_eq!(app.filter, Filter::All);
+    }
+}
@@ -0,0 +1,57 @@
+//! TodoMVC in the terminal, with vim-style keys.
+
+use std::io::{self, Stdout};
+
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+
+mod app;
+mod ui;
+
+use app::App;
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+fn main() -> io::Result<()> {
+    // Give the terminal back before a panic message gets printed into the alternate screen
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+
+    let mut terminal = setup_terminal()?;
+    let result = run(&mut terminal);
+    restore_terminal()?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run(terminal: &mut Term) -> io::Result<()> {
+    let mut app = App::new();
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        if let Event::Key(key) = event::read()? {
+            // Windows also reports releases
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn setup_terminal() -> io::Result<Term> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
@@ -0,0 +1,133 @@
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use todo_core::Filter;
+
+use crate::app::{App, Mode};
+
+const ACCENT: Color = Color::Rgb(0xb8, 0x3f, 0x45);
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+    let &[title, input, list, footer, help] = &*chunks else {
+        return;
+    };
+
+    frame.render_widget(
+        Paragraph::new("todos")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)),
+        title,
+    );
+
+    draw_input(frame, app, input);
+    draw_list(frame, app, list);
+    draw_footer(frame, app, footer);
+
+    let keys = match app.mode {
+        Mode::Normal => "j/k move · space toggle · e edit · dd delete · i new · A toggle all · 1-3/f filter · C clear completed · q quit",
+        Mode::Insert => "enter add · esc done",
+        Mode::Edit { .. } => "enter save (empty deletes) · esc cancel",
+    };
+    frame.render_widget(Paragraph::new(keys).style(Style::default().fg(Color::DarkGray)), help);
+}
+
+fn draw_input(frame: &mut Frame, app: &App, area: Rect) {
+    let focused = app.mode == Mode::Insert;
+    let (text, style) = if app.input.is_empty() && !focused {
+        ("What needs to be done?", Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC))
+    } else {
+        (app.input.as_str(), Style::default())
+    };
+    let border = if focused { Style::default().fg(ACCENT) } else { Style::default() };
+
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).border_style(border)),
+        area,
+    );
+    if focused {
+        frame.set_cursor(area.x + 1 + app.input.chars().count() as u16, area.y + 1);
+    }
+}
+
+fn draw_list(frame: &mut Frame, app: &App, area: Rect) {
+    const HIGHLIGHT_SYMBOL: &str = "› ";
+    const CHECKBOX_WIDTH: usize = 4;
+
+    let visible = app.visible();
+    let items = visible.iter()
+        .filter_map(|&index| Some((index, app.tasks.get(index)?)))
+        .map(|(index, task)| {
+            let checkbox = if task.completed { "[x] " } else { "[ ] " };
+            match &app.mode {
+                Mode::Edit { index: editing, buffer } if *editing == index => {
+                    ListItem::new(Line::from(vec![
+                        Span::raw(checkbox),
+                        Span::styled(buffer.clone(), Style::default().add_modifier(Modifier::UNDERLINED)),
+                    ]))
+                }
+                _ => {
+                    let style = if task.completed {
+                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(checkbox),
+                        Span::styled(task.description.clone(), style),
+                    ]))
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::Rgb(0x30, 0x30, 0x30)))
+        .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+    let mut state = ListState::default();
+    if app.mode != Mode::Insert && !visible.is_empty() {
+        state.select(Some(app.selected));
+    }
+    frame.render_stateful_widget(list, area, &mut state);
+
+    // The row being edited is always the selected one; the offset is only known after rendering
+    if let Mode::Edit { buffer, .. } = &app.mode {
+        let column = HIGHLIGHT_SYMBOL.chars().count() + CHECKBOX_WIDTH + buffer.chars().count();
+        let row = app.selected.saturating_sub(state.offset());
+        frame.set_cursor(area.x + 1 + column as u16, area.y + 1 + row as u16);
+    }
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let mut spans = vec![
+        Span::raw(todo_core::tasks_left_label(app.tasks.active_count())),
+        Span::raw("   "),
+    ];
+    for (i, filter) in Filter::ALL.into_iter().enumerate() {
+        let style = if filter == app.filter {
+            Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(format!("{} {}", i + 1, filter.label()), style));
+        spans.push(Span::raw("  "));
+    }
+    if app.tasks.has_completed() {
+        spans.push(Span::raw(" C Clear completed"));
+    }
+
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}